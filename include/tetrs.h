@@ -0,0 +1,64 @@
+/* C interface of the tetrs environment, exported by the cdylib (libtetrs.so, tetrs.dll,
+ * libtetrs.dylib). See src/ffi.rs for the implementation.
+ *
+ * Piece kinds are passed as their index in I, L, J, S, Z, O, T order and -1 for none.
+ * Every function but tetrs_env_new and tetrs_env_free needs a live, non-null environment. */
+
+#ifndef TETRS_H
+#define TETRS_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct TetrsEnv TetrsEnv;
+
+/* Slots in the preview of an observation, those past the ruleset's preview hold -1. */
+#define TETRS_PREVIEW_SLOTS 6
+
+typedef struct TetrsQueue {
+    int8_t current;
+    int8_t preview[TETRS_PREVIEW_SLOTS];
+    int8_t hold;
+} TetrsQueue;
+
+typedef struct TetrsStep {
+    float reward;
+    bool done;
+    /* the action couldn't be played, nothing changed */
+    bool invalid;
+} TetrsStep;
+
+/* A new environment on a board of rows by cols, or NULL when no game fits that size:
+ * it needs at least 4 of each and at most 1000. */
+TetrsEnv *tetrs_env_new(int64_t rows, int64_t cols, uint64_t seed);
+
+/* Frees an environment from tetrs_env_new or tetrs_env_clone, NULL is ignored. */
+void tetrs_env_free(TetrsEnv *env);
+
+TetrsEnv *tetrs_env_clone(const TetrsEnv *env);
+
+void tetrs_env_reset(TetrsEnv *env, uint64_t seed);
+
+size_t tetrs_env_action_count(const TetrsEnv *env);
+
+TetrsStep tetrs_env_step(TetrsEnv *env, size_t action);
+
+/* Writes rows * cols cells, row major with 1 for filled, and the current piece, the preview
+ * and the held piece to queue. Returns how many cells were written, at most board_len. */
+size_t tetrs_env_observe(const TetrsEnv *env, uint8_t *board, size_t board_len,
+                         TetrsQueue *queue);
+
+/* Writes 1 for every action that can be played right now. Returns how many were written, at
+ * most mask_len. */
+size_t tetrs_env_action_mask(const TetrsEnv *env, uint8_t *mask, size_t mask_len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,184 @@
+use std::time::Duration;
+
+use crate::{Block, Position, events::GameEvent, scoring::SpinKind};
+
+// how long each animation runs; a line clear lasts the engine's line clear delay, which
+// LinesCleared carries, and ends when the next piece spawns
+pub const LOCK_FLASH_DURATION: Duration = Duration::from_millis(120);
+pub const TRAIL_DURATION: Duration = Duration::from_millis(150);
+pub const SHAKE_DURATION: Duration = Duration::from_millis(300);
+pub const POPUP_DURATION: Duration = Duration::from_millis(1200);
+// attack that starts shaking the board, every line more shakes it harder
+pub const SHAKE_ATTACK: usize = 4;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Effect {
+    // the full rows and what they hold, still on the board while they dissolve
+    LineClear { rows: Vec<isize>, cells: Vec<Block> },
+    LockFlash { cells: Vec<Position> },
+    // cells are where the piece landed, it came down `rows` rows
+    Trail { cells: Vec<Position>, rows: usize },
+    // strength is in cells
+    Shake { strength: f32 },
+    // what the clear was, popups stack up in the order they came
+    Popup { text: String },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Animation {
+    pub effect: Effect,
+    elapsed: Duration,
+    duration: Duration,
+}
+
+impl Animation {
+    // 0 when it starts, 1 when it is over
+    pub fn progress(&self) -> f32 {
+        (self.elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+    }
+}
+
+// the name of a clear as it is called out, "T-SPIN MINI SINGLE", "TETRIS" and so on
+pub fn clear_name(lines: usize, spin: SpinKind) -> String {
+    let lines = ["", "SINGLE", "DOUBLE", "TRIPLE", "TETRIS"][lines.min(4)];
+    match spin {
+        SpinKind::None => lines.to_string(),
+        SpinKind::Mini => format!("T-SPIN MINI {lines}").trim_end().to_string(),
+        SpinKind::Full => format!("T-SPIN {lines}").trim_end().to_string(),
+    }
+}
+
+// the animations the engine's events call for; they run on game time, so they wait out pauses
+// with the game and stay in step with it
+#[derive(Clone, Debug, Default)]
+pub struct Animations {
+    running: Vec<Animation>,
+}
+
+impl Animations {
+    pub fn new() -> Animations {
+        Animations::default()
+    }
+
+    pub fn observe(&mut self, events: &[GameEvent]) {
+        let cleared = events
+            .iter()
+            .any(|event| matches!(event, GameEvent::LinesCleared { .. }));
+        for event in events {
+            match event {
+                GameEvent::HardDropped { rows, cells } if *rows > 0 => {
+                    self.start(
+                        Effect::Trail {
+                            cells: cells.clone(),
+                            rows: *rows,
+                        },
+                        TRAIL_DURATION,
+                    );
+                }
+                GameEvent::PieceSpawned { .. } => {
+                    self.running
+                        .retain(|animation| !matches!(animation.effect, Effect::LineClear { .. }));
+                }
+                GameEvent::Locked { cells, spin, .. } => {
+                    self.start(
+                        Effect::LockFlash {
+                            cells: cells.clone(),
+                        },
+                        LOCK_FLASH_DURATION,
+                    );
+                    // a spin that cleared nothing gets no LinesCleared to call it out
+                    if *spin != SpinKind::None && !cleared {
+                        self.popup(clear_name(0, *spin));
+                    }
+                }
+                GameEvent::LinesCleared {
+                    rows,
+                    cells,
+                    delay_ms,
+                    spin,
+                    back_to_back,
+                    combo,
+                    perfect_clear,
+                    attack,
+                    ..
+                } => {
+                    // rows that went at once have nothing left to dissolve
+                    if *delay_ms > 0 {
+                        self.start(
+                            Effect::LineClear {
+                                rows: rows.clone(),
+                                cells: cells.clone(),
+                            },
+                            Duration::from_millis(*delay_ms as u64),
+                        );
+                    }
+                    self.popup(clear_name(rows.len(), *spin));
+                    if *back_to_back {
+                        self.popup("B2B".to_string());
+                    }
+                    if let Some(combo) = combo.filter(|combo| *combo > 0) {
+                        self.popup(format!("{combo} COMBO"));
+                    }
+                    if *perfect_clear {
+                        self.popup("PERFECT CLEAR".to_string());
+                    }
+                    if *attack >= SHAKE_ATTACK {
+                        let strength = 0.2 + 0.1 * (attack - SHAKE_ATTACK) as f32;
+                        self.start(Effect::Shake { strength }, SHAKE_DURATION);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // moves every animation on by the game time that passed, dropping those that are over
+    pub fn advance(&mut self, time: Duration) {
+        for animation in &mut self.running {
+            animation.elapsed += time;
+        }
+        self.running
+            .retain(|animation| animation.elapsed < animation.duration);
+    }
+
+    pub fn running(&self) -> &[Animation] {
+        &self.running
+    }
+
+    // the rows a running clear animation still shows, numbered as before clearing
+    pub fn clearing_rows(&self) -> &[isize] {
+        self.running
+            .iter()
+            .find_map(|animation| match &animation.effect {
+                Effect::LineClear { rows, .. } => Some(rows.as_slice()),
+                _ => None,
+            })
+            .unwrap_or(&[])
+    }
+
+    // how far the board is pushed off its place, in cells
+    pub fn shake_offset(&self) -> (f32, f32) {
+        self.running
+            .iter()
+            .filter_map(|animation| match animation.effect {
+                Effect::Shake { strength } => {
+                    let left = strength * (1.0 - animation.progress());
+                    let angle = animation.elapsed.as_secs_f32() * 60.0;
+                    Some((left * angle.sin(), left * (angle * 1.3).cos()))
+                }
+                _ => None,
+            })
+            .fold((0.0, 0.0), |(x, y), (dx, dy)| (x + dx, y + dy))
+    }
+
+    fn start(&mut self, effect: Effect, duration: Duration) {
+        self.running.push(Animation {
+            effect,
+            elapsed: Duration::ZERO,
+            duration,
+        });
+    }
+    fn popup(&mut self, text: String) {
+        self.start(Effect::Popup { text }, POPUP_DURATION);
+    }
+}
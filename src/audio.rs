@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+
+use macroquad::audio::{
+    PlaySoundParams, Sound, load_sound_from_bytes, play_sound, set_sound_volume, stop_sound,
+};
+
+use crate::{events::GameEvent, scoring::SpinKind};
+
+const SAMPLE_RATE: u32 = 22_050;
+// pitch steps of the combo sound, longer combos stay on the last one
+pub const COMBO_STEPS: usize = 12;
+// music plays this much quieter than the effects
+const MUSIC_VOLUME: f32 = 0.3;
+const MUSIC_BEAT_SECS: f32 = 0.4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundEffect {
+    Move,
+    Rotate,
+    Lock,
+    HardDrop,
+    LineClear,
+    Tetris,
+    TSpin,
+    // the pitch goes up a semitone for every step
+    Combo(usize),
+    Hold,
+    LevelUp,
+    GameOver,
+}
+
+impl SoundEffect {
+    fn all() -> impl Iterator<Item = SoundEffect> {
+        [
+            SoundEffect::Move,
+            SoundEffect::Rotate,
+            SoundEffect::Lock,
+            SoundEffect::HardDrop,
+            SoundEffect::LineClear,
+            SoundEffect::Tetris,
+            SoundEffect::TSpin,
+            SoundEffect::Hold,
+            SoundEffect::LevelUp,
+            SoundEffect::GameOver,
+        ]
+        .into_iter()
+        .chain((0..COMBO_STEPS).map(SoundEffect::Combo))
+    }
+
+    // (start hz, end hz, seconds) segments played one after the other
+    fn segments(self) -> (Wave, Vec<(f32, f32, f32)>) {
+        let arpeggio = |notes: &[u8], secs: f32| {
+            notes
+                .iter()
+                .map(|note| (note_hz(*note), note_hz(*note), secs))
+                .collect()
+        };
+        match self {
+            SoundEffect::Move => (Wave::Square, vec![(880., 880., 0.025)]),
+            SoundEffect::Rotate => (Wave::Triangle, vec![(660., 990., 0.05)]),
+            SoundEffect::Lock => (Wave::Square, vec![(160., 120., 0.06)]),
+            SoundEffect::HardDrop => (Wave::Square, vec![(400., 80., 0.1)]),
+            SoundEffect::LineClear => (Wave::Triangle, arpeggio(&[72, 76, 79], 0.06)),
+            SoundEffect::Tetris => (Wave::Triangle, arpeggio(&[72, 76, 79, 84, 88], 0.07)),
+            SoundEffect::TSpin => (Wave::Square, arpeggio(&[74, 77, 81, 86], 0.07)),
+            SoundEffect::Combo(step) => {
+                let hz = note_hz(72 + step.min(COMBO_STEPS - 1) as u8);
+                (Wave::Triangle, vec![(hz, hz * 1.5, 0.08)])
+            }
+            SoundEffect::Hold => (Wave::Triangle, vec![(440., 660., 0.06)]),
+            SoundEffect::LevelUp => (Wave::Triangle, arpeggio(&[72, 76, 79, 84, 88, 91], 0.06)),
+            SoundEffect::GameOver => (Wave::Square, arpeggio(&[67, 64, 60, 55], 0.2)),
+        }
+    }
+}
+
+// the sounds a step of the game makes, each once however often it happened in the step
+pub fn effects(events: &[GameEvent]) -> Vec<SoundEffect> {
+    let mut effects = Vec::new();
+    let hard_dropped = events
+        .iter()
+        .any(|event| matches!(event, GameEvent::HardDropped { .. }));
+    for event in events {
+        let effect = match event {
+            GameEvent::Moved { .. } => Some(SoundEffect::Move),
+            GameEvent::Rotated { .. } => Some(SoundEffect::Rotate),
+            GameEvent::HardDropped { .. } => Some(SoundEffect::HardDrop),
+            // a hard drop already made its own sound
+            GameEvent::Locked { .. } if !hard_dropped => Some(SoundEffect::Lock),
+            GameEvent::LinesCleared {
+                rows, spin, combo, ..
+            } => {
+                if let Some(combo) = combo.filter(|combo| *combo > 0) {
+                    effects.push(SoundEffect::Combo(combo - 1));
+                }
+                Some(if *spin != SpinKind::None {
+                    SoundEffect::TSpin
+                } else if rows.len() >= 4 {
+                    SoundEffect::Tetris
+                } else {
+                    SoundEffect::LineClear
+                })
+            }
+            GameEvent::Hold { .. } => Some(SoundEffect::Hold),
+            GameEvent::LevelUp { .. } => Some(SoundEffect::LevelUp),
+            GameEvent::GameOver => Some(SoundEffect::GameOver),
+            _ => None,
+        };
+        effects.extend(effect);
+    }
+    let mut played = Vec::new();
+    effects.retain(|effect| {
+        let first = !played.contains(effect);
+        played.push(*effect);
+        first
+    });
+    effects
+}
+
+// sound effects for the engine's events and looping background music, all synthesized at load
+pub struct Audio {
+    sounds: HashMap<SoundEffect, Sound>,
+    music: Option<Sound>,
+    music_playing: bool,
+    volume: f32,
+    muted: bool,
+}
+
+impl Audio {
+    // a sound that fails to load stays silent, the game doesn't need it
+    pub async fn load(volume: f32, muted: bool) -> Audio {
+        let mut sounds = HashMap::new();
+        for effect in SoundEffect::all() {
+            let (wave, segments) = effect.segments();
+            if let Ok(sound) = load_sound_from_bytes(&wav(&synth(wave, &segments))).await {
+                sounds.insert(effect, sound);
+            }
+        }
+        let music = load_sound_from_bytes(&wav(&music())).await.ok();
+        Audio {
+            sounds,
+            music,
+            music_playing: false,
+            volume,
+            muted,
+        }
+    }
+
+    pub fn play(&self, events: &[GameEvent]) {
+        if self.muted {
+            return;
+        }
+        for effect in effects(events) {
+            if let Some(sound) = self.sounds.get(&effect) {
+                play_sound(
+                    sound,
+                    PlaySoundParams {
+                        looped: false,
+                        volume: self.volume,
+                    },
+                );
+            }
+        }
+    }
+
+    pub fn start_music(&mut self) {
+        if let Some(music) = &self.music {
+            stop_sound(music);
+            play_sound(
+                music,
+                PlaySoundParams {
+                    looped: true,
+                    volume: self.music_volume(),
+                },
+            );
+            self.music_playing = true;
+        }
+    }
+    pub fn stop_music(&mut self) {
+        if let Some(music) = &self.music {
+            stop_sound(music);
+        }
+        self.music_playing = false;
+    }
+
+    pub fn set_volume(&mut self, volume: f32, muted: bool) {
+        if (volume, muted) == (self.volume, self.muted) {
+            return;
+        }
+        self.volume = volume;
+        self.muted = muted;
+        if let Some(music) = self.music.as_ref().filter(|_| self.music_playing) {
+            set_sound_volume(music, self.music_volume());
+        }
+    }
+
+    fn music_volume(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.volume * MUSIC_VOLUME
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Wave {
+    Square,
+    Triangle,
+}
+
+// equal temperament, 69 is A4
+fn note_hz(note: u8) -> f32 {
+    440. * 2f32.powf((note as f32 - 69.) / 12.)
+}
+
+// every segment sweeps from its start to its end frequency and fades out towards its end
+fn synth(wave: Wave, segments: &[(f32, f32, f32)]) -> Vec<f32> {
+    let mut samples = Vec::new();
+    let mut phase = 0f32;
+    for &(start_hz, end_hz, secs) in segments {
+        let count = (secs * SAMPLE_RATE as f32) as usize;
+        for i in 0..count {
+            let progress = i as f32 / count as f32;
+            let hz = start_hz + (end_hz - start_hz) * progress;
+            phase = (phase + hz / SAMPLE_RATE as f32).fract();
+            let value = match wave {
+                Wave::Square => {
+                    if phase < 0.5 {
+                        0.5
+                    } else {
+                        -0.5
+                    }
+                }
+                Wave::Triangle => 1. - 4. * (phase - 0.5).abs(),
+            };
+            samples.push(value * (1. - progress).powi(2));
+        }
+    }
+    samples
+}
+
+// Korobeiniki, the folk song the game is known for, as (midi note or 0 for a rest, beats)
+const MELODY: [(u8, f32); 38] = [
+    (76, 1.),
+    (71, 0.5),
+    (72, 0.5),
+    (74, 1.),
+    (72, 0.5),
+    (71, 0.5),
+    (69, 1.),
+    (69, 0.5),
+    (72, 0.5),
+    (76, 1.),
+    (74, 0.5),
+    (72, 0.5),
+    (71, 1.5),
+    (72, 0.5),
+    (74, 1.),
+    (76, 1.),
+    (72, 1.),
+    (69, 1.),
+    (69, 2.),
+    (0, 0.5),
+    (74, 1.),
+    (77, 0.5),
+    (81, 1.),
+    (79, 0.5),
+    (77, 0.5),
+    (76, 1.5),
+    (72, 0.5),
+    (76, 1.),
+    (74, 0.5),
+    (72, 0.5),
+    (71, 1.),
+    (71, 0.5),
+    (72, 0.5),
+    (74, 1.),
+    (76, 1.),
+    (72, 1.),
+    (69, 1.),
+    (69, 1.5),
+];
+
+fn music() -> Vec<f32> {
+    MELODY
+        .iter()
+        .flat_map(|&(note, beats)| {
+            let secs = beats * MUSIC_BEAT_SECS;
+            if note == 0 {
+                vec![0.; (secs * SAMPLE_RATE as f32) as usize]
+            } else {
+                let hz = note_hz(note);
+                // a short gap keeps repeated notes apart
+                let mut samples = synth(Wave::Square, &[(hz, hz, secs * 0.9)]);
+                samples.resize((secs * SAMPLE_RATE as f32) as usize, 0.);
+                samples
+            }
+        })
+        .collect()
+}
+
+// 16 bit mono PCM in a RIFF container
+fn wav(samples: &[f32]) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, one channel
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        let value = (sample.clamp(-1., 1.) * i16::MAX as f32) as i16;
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes
+}
@@ -0,0 +1,29 @@
+use std::time::Instant;
+
+use tetrs::{
+    bot::{Bot, Weights},
+    ruleset::Ruleset,
+};
+
+fn main() {
+    let pieces: usize = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(1000);
+
+    let mut game_state = Ruleset::default().new_game();
+    let mut bot = Bot::new(Weights::default());
+
+    let start = Instant::now();
+    while game_state.pieces_placed() < pieces && bot.play_piece(&mut game_state) {}
+    let elapsed = start.elapsed().as_secs_f64();
+
+    println!(
+        "pieces: {}, lines: {}, topped out: {}, {:.2}s ({:.1} pieces/s)",
+        game_state.pieces_placed(),
+        game_state.lines_cleared(),
+        game_state.is_game_over(),
+        elapsed,
+        game_state.pieces_placed() as f64 / elapsed
+    );
+}
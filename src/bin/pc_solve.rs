@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+
+use tetrs::{
+    Block, GARBAGE_COLOR, Playfield,
+    diagram::{Diagram, parse_queue},
+    pc_solver::{DEFAULT_MAX_HEIGHT, solve},
+    ruleset::Ruleset,
+};
+
+const DEFAULT_MAX_SOLUTIONS: usize = 5;
+const USAGE: &str = "usage: pc_solve BOARD [--queue PIECES] [--hold PIECE] [--height ROWS] [--max SOLUTIONS] [--ruleset FILE]";
+
+// pc_solve BOARD [--queue PIECES] [--hold PIECE] [--height ROWS] [--max SOLUTIONS] [--ruleset FILE]
+// the board is a fumen or text file (or "-" for an empty one), the first piece of the queue is
+// the current one; the queue defaults to the one the board comes with. The board size and
+// rotation system come from the ruleset, the default one without --ruleset
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+    let value_after = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|i| args.get(i + 1))
+    };
+    let Some(path) = args.get(1) else {
+        eprintln!("{USAGE}");
+        std::process::exit(2);
+    };
+    let ruleset = match value_after("--ruleset") {
+        Some(path) => Ruleset::load(path)?,
+        None => Ruleset::default(),
+    };
+    let size = ruleset.size();
+
+    let mut diagram = match path.as_str() {
+        "-" => Diagram::default(),
+        path => Diagram::parse(&std::fs::read_to_string(path)?)?,
+    };
+    if let Some(queue) = value_after("--queue") {
+        diagram.queue = parse_queue(queue)?;
+    }
+    let hold = match value_after("--hold") {
+        Some(hold) => parse_queue(hold)?.first().copied(),
+        None => None,
+    };
+    let height = value_after("--height")
+        .and_then(|height| height.parse().ok())
+        .unwrap_or(DEFAULT_MAX_HEIGHT);
+    let max_solutions = value_after("--max")
+        .and_then(|max| max.parse().ok())
+        .unwrap_or(DEFAULT_MAX_SOLUTIONS);
+
+    let Some((current, queue)) = diagram.queue.split_first() else {
+        eprintln!("no pieces to play, give them with --queue");
+        std::process::exit(2);
+    };
+    if diagram
+        .cells
+        .iter()
+        .any(|cell| cell.x >= size.cols || cell.y >= size.rows)
+    {
+        eprintln!(
+            "the board doesn't fit in {} rows of {}, give a ruleset that it fits with --ruleset",
+            size.rows, size.cols
+        );
+        std::process::exit(2);
+    }
+    let mut playfield = Playfield::new(size).with_hidden_rows(ruleset.hidden_rows);
+    let cells: HashSet<Block> = diagram
+        .cells
+        .iter()
+        .map(|cell| Block {
+            color: GARBAGE_COLOR,
+            coordinates: cell.position(size.rows),
+        })
+        .collect();
+    playfield.put_blocks(&cells);
+
+    let solutions = solve(
+        &playfield,
+        ruleset.rotation,
+        *current,
+        queue,
+        hold,
+        height,
+        max_solutions,
+    );
+    if solutions.is_empty() {
+        println!("no perfect clear");
+    }
+    for (i, solution) in solutions.iter().enumerate() {
+        println!("solution {} ({} rows):", i + 1, solution.height);
+        for piece in &solution.pieces {
+            println!(
+                "  {}{:?} {:?} {:?}",
+                if piece.hold { "hold, " } else { "" },
+                piece.kind,
+                piece.rotation_state,
+                piece.inputs
+            );
+        }
+        print!("{}", solution.to_diagram(&playfield).to_text());
+        println!("{}", solution.to_fumen(&playfield));
+    }
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use macroquad::prelude::*;
+use tetrs::{
+    Position,
+    diagram::piece_char,
+    settings::Settings,
+    spectate::{Snapshot, Viewer},
+};
+
+// the hidden rows are left out, so the first visible row is drawn at `origin`
+fn draw_cell(
+    settings: &Settings,
+    snapshot: &Snapshot,
+    origin: Vec2,
+    row: isize,
+    col: isize,
+    color: Color,
+) {
+    if row < snapshot.hidden_rows {
+        return;
+    }
+    let step = settings.cell_size + settings.grid_spacing;
+    draw_rectangle(
+        origin.x + col as f32 * step,
+        origin.y + (row - snapshot.hidden_rows) as f32 * step,
+        settings.cell_size,
+        settings.cell_size,
+        color,
+    );
+}
+
+fn draw_snapshot(settings: &Settings, snapshot: &Snapshot, origin: Vec2) {
+    for row in 0..snapshot.rows {
+        for col in 0..snapshot.cols {
+            draw_cell(settings, snapshot, origin, row, col, GRAY);
+        }
+    }
+    for cell in snapshot.cells.iter().chain(&snapshot.status.active.cells) {
+        let Position { row, col } = cell.position;
+        draw_cell(settings, snapshot, origin, row, col, cell.color());
+    }
+
+    let status = &snapshot.status;
+    let hold = status
+        .hold
+        .map_or("-".to_string(), |kind| format!("{kind:?}"));
+    let preview: String = status.preview.iter().copied().map(piece_char).collect();
+    let lines = [
+        format!("next: {preview}"),
+        format!("hold: {hold}"),
+        format!("score: {}", status.score),
+        format!("level: {}", status.level),
+        format!("lines: {}", status.lines),
+    ];
+    let x = origin.x + snapshot.cols as f32 * (settings.cell_size + settings.grid_spacing) + 20.;
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(line, x, origin.y + 10. + i as f32 * 20., 20., WHITE);
+    }
+    if status.game_over {
+        draw_text("game over", x, origin.y + 130., 24., WHITE);
+    }
+}
+
+// watches a game started with --broadcast PORT, takes the address as its only argument
+#[macroquad::main("tetrs spectator")]
+async fn main() {
+    let (settings, problems) = Settings::load();
+    for problem in &problems {
+        eprintln!("settings: {problem}");
+    }
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:7879".to_string());
+    let mut viewer = match Viewer::connect(&address) {
+        Ok(viewer) => viewer,
+        Err(error) => {
+            eprintln!("can't watch {address}: {error}");
+            return;
+        }
+    };
+
+    let mut connected = true;
+    loop {
+        if connected {
+            connected = viewer.poll().unwrap_or(false);
+        }
+        clear_background(BLACK);
+        match viewer.state() {
+            Some(snapshot) => draw_snapshot(&settings, snapshot, vec2(50., 50.)),
+            None => {
+                draw_text("waiting for the first snapshot", 50., 50., 20., WHITE);
+            }
+        }
+        if !connected {
+            draw_text("the game has ended the stream", 50., 380., 24., WHITE);
+        }
+        next_frame().await;
+    }
+}
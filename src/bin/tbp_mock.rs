@@ -0,0 +1,112 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    io::{self, BufReader},
+};
+
+use tetrs::{
+    Block, Playfield, PlayfieldSize, Position,
+    bot::{Bot, Weights},
+    move_generator::{CollisionMap, ShapeTable, generate_placements},
+    ruleset::RotationSystem,
+    scoring::SpinKind,
+    tbp::{
+        BOARD_COLS, BOARD_ROWS, BotMessage, FrontendMessage, Move, PieceLocation, read_message,
+        write_message,
+    },
+};
+
+const ROWS: isize = BOARD_ROWS as isize;
+
+// a minimal TBP bot for trying the frontend: greedy, one piece deep and never holds
+fn best_move(playfield: &Playfield, queue: &VecDeque<tetrs::TetraminoKind>) -> Option<Move> {
+    let kind = *queue.front()?;
+    let bot = Bot::new(Weights::default());
+    let collision_map = CollisionMap::new(playfield);
+    let shapes = ShapeTable::new(kind);
+    // the protocol's standard rules rotate with SRS
+    generate_placements(playfield, kind, RotationSystem::Srs)
+        .into_iter()
+        .map(|placement| {
+            let mut after = collision_map.clone();
+            let lines = after.place(shapes.cells(placement.rotation_state), placement.offset);
+            (bot.evaluate(&after, lines), placement)
+        })
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, placement)| {
+            let cells: Vec<Position> = placement.blocks.iter().map(|b| b.coordinates).collect();
+            Move {
+                location: PieceLocation::from_cells(kind, placement.rotation_state, &cells, ROWS),
+                spin: if placement.is_spin() {
+                    SpinKind::Full
+                } else {
+                    SpinKind::None
+                },
+            }
+        })
+}
+
+fn main() -> io::Result<()> {
+    let mut input = BufReader::new(io::stdin().lock());
+    let mut output = io::stdout().lock();
+    let size = PlayfieldSize {
+        rows: ROWS,
+        cols: BOARD_COLS as isize,
+    };
+    let mut playfield = Playfield::new(size);
+    let mut queue = VecDeque::new();
+
+    write_message(
+        &mut output,
+        &BotMessage::Info {
+            name: "tetrs mock".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            author: "tetrs".to_string(),
+            features: Vec::new(),
+        },
+    )?;
+    while let Ok(Some(message)) = read_message::<FrontendMessage>(&mut input) {
+        match message {
+            FrontendMessage::Rules => write_message(&mut output, &BotMessage::Ready)?,
+            FrontendMessage::Start {
+                queue: pieces,
+                board,
+                ..
+            } => {
+                playfield = Playfield::new(size);
+                let blocks: HashSet<Block> = board
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(y, row)| {
+                        row.iter()
+                            .enumerate()
+                            .filter(|(_, cell)| cell.is_some())
+                            .map(move |(x, _)| {
+                                Block::from(Position::new(ROWS - 1 - y as isize, x as isize))
+                            })
+                    })
+                    .collect();
+                playfield.put_blocks(&blocks);
+                queue = pieces.into();
+            }
+            FrontendMessage::Suggest => {
+                let moves = best_move(&playfield, &queue).into_iter().collect();
+                write_message(&mut output, &BotMessage::Suggestion { moves })?;
+            }
+            FrontendMessage::Play { mv } => {
+                let blocks: HashSet<Block> = mv
+                    .location
+                    .cells(ROWS)
+                    .into_iter()
+                    .map(Block::from)
+                    .collect();
+                playfield.put_blocks(&blocks);
+                playfield.clear_full_rows();
+                queue.pop_front();
+            }
+            FrontendMessage::NewPiece { piece } => queue.push_back(piece),
+            FrontendMessage::Stop => queue.clear(),
+            FrontendMessage::Quit => break,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,234 @@
+use std::collections::HashSet;
+
+use crate::{
+    GameState, InputEvent, KeyMap, Position, RotationState,
+    move_generator::{CollisionMap, Placement, generate_placements_from},
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Weights {
+    pub holes: f32,
+    pub bumpiness: f32,
+    pub aggregate_height: f32,
+    pub wells: f32,
+    pub t_slots: f32,
+    pub lines: f32,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            holes: -3.6,
+            bumpiness: -1.8,
+            aggregate_height: -5.1,
+            wells: -0.5,
+            t_slots: 1.5,
+            lines: 7.6,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Decision {
+    pub hold: bool,
+    pub placement: Placement,
+}
+
+pub struct Bot {
+    weights: Weights,
+    target: Option<Decision>,
+    pieces_seen: usize,
+}
+
+impl Bot {
+    pub fn new(weights: Weights) -> Bot {
+        Bot {
+            weights,
+            target: None,
+            pieces_seen: 0,
+        }
+    }
+
+    pub fn weights(&self) -> &Weights {
+        &self.weights
+    }
+
+    pub fn evaluate(&self, collision_map: &CollisionMap, lines: usize) -> f32 {
+        let heights = collision_map.column_heights();
+        let size = collision_map.size();
+
+        let aggregate_height: isize = heights.iter().sum();
+        let bumpiness: isize = heights.windows(2).map(|w| (w[0] - w[1]).abs()).sum();
+        let holes = (0..size.cols)
+            .map(|col| {
+                (size.rows - heights[col as usize]..size.rows)
+                    .filter(|row| !collision_map.is_occupied(Position::new(*row, col)))
+                    .count()
+            })
+            .sum::<usize>();
+        let wells: isize = (0..heights.len())
+            .map(|col| {
+                let left = if col == 0 {
+                    isize::MAX
+                } else {
+                    heights[col - 1]
+                };
+                let right = heights.get(col + 1).copied().unwrap_or(isize::MAX);
+                (left.min(right) - heights[col]).max(0)
+            })
+            .sum();
+
+        self.weights.aggregate_height * aggregate_height as f32
+            + self.weights.bumpiness * bumpiness as f32
+            + self.weights.holes * holes as f32
+            + self.weights.wells * wells as f32
+            + self.weights.t_slots * count_t_slots(collision_map) as f32
+            + self.weights.lines * lines as f32
+    }
+
+    // two ply search: every placement of the piece about to be played is scored by the best
+    // placement of the piece that follows it, with and without using hold
+    pub fn decide(&self, game_state: &GameState) -> Option<Decision> {
+        let collision_map = CollisionMap::new(game_state.playfield());
+        let spawn_offset = game_state.playfield().spawn_offset();
+        let active = game_state.current_tetramino();
+        let next = game_state.next_kind();
+
+        let mut options = vec![(
+            false,
+            active.kind(),
+            active.rotation_state(),
+            active.offset(),
+            Some(next),
+        )];
+        if game_state.can_hold() {
+            options.push(match game_state.hold_kind() {
+                Some(held) => (true, held, RotationState::Init, spawn_offset, Some(next)),
+                None => (true, next, RotationState::Init, spawn_offset, None),
+            });
+        }
+
+        let mut best: Option<(f32, Decision)> = None;
+        for (hold, kind, rotation_state, offset, lookahead) in options {
+            let shapes = game_state.shape_table(kind);
+            let lookahead_shapes = lookahead.map(|kind| game_state.shape_table(kind));
+            for placement in
+                generate_placements_from(&collision_map, &shapes, rotation_state, offset)
+            {
+                let mut after = collision_map.clone();
+                let lines = after.place(shapes.cells(placement.rotation_state), placement.offset);
+                let score = match &lookahead_shapes {
+                    Some(lookahead_shapes) => generate_placements_from(
+                        &after,
+                        lookahead_shapes,
+                        RotationState::Init,
+                        spawn_offset,
+                    )
+                    .iter()
+                    .map(|second| {
+                        let mut after_second = after.clone();
+                        let second_lines = after_second
+                            .place(lookahead_shapes.cells(second.rotation_state), second.offset);
+                        self.evaluate(&after_second, lines + second_lines)
+                    })
+                    .fold(f32::MIN, f32::max),
+                    None => self.evaluate(&after, lines),
+                };
+                if best
+                    .as_ref()
+                    .is_none_or(|(best_score, _)| score > *best_score)
+                {
+                    best = Some((score, Decision { hold, placement }));
+                }
+            }
+        }
+        best.map(|(_, decision)| decision)
+    }
+
+    // plays one piece straight through GameState, without waiting on timers
+    pub fn play_piece(&mut self, game_state: &mut GameState) -> bool {
+        if game_state.is_game_over() {
+            return false;
+        }
+        let Some(decision) = self.decide(game_state) else {
+            return false;
+        };
+        if decision.hold {
+            game_state.hold();
+        }
+        for input in decision.placement.inputs_before_hard_drop() {
+            game_state.apply_input(*input);
+        }
+        game_state.hard_drop();
+        true
+    }
+
+    // one key per frame, as a human would press them; the path is searched again from wherever
+    // the piece is now, so gravity moving it between frames does not break the plan
+    pub fn next_input(&mut self, game_state: &GameState) -> InputEvent {
+        let mut keys = HashSet::new();
+        if game_state.is_game_over() {
+            return InputEvent::pressed(keys);
+        }
+        if self.target.is_none() || self.pieces_seen != game_state.pieces_placed() {
+            self.target = self.decide(game_state);
+            self.pieces_seen = game_state.pieces_placed();
+        }
+        let Some(target) = &self.target else {
+            return InputEvent::pressed(keys);
+        };
+
+        if target.hold {
+            if game_state.can_hold() {
+                keys.insert(KeyMap::default().hold);
+            }
+            self.target = None;
+            return InputEvent::pressed(keys);
+        }
+
+        let key_map = KeyMap::default();
+        let active = game_state.current_tetramino();
+        let path = generate_placements_from(
+            &CollisionMap::new(game_state.playfield()),
+            &game_state.shape_table(active.kind()),
+            active.rotation_state(),
+            active.offset(),
+        )
+        .into_iter()
+        .find(|placement| placement.blocks == target.placement.blocks);
+        match path {
+            Some(placement) => {
+                keys.insert(
+                    placement
+                        .inputs_before_hard_drop()
+                        .first()
+                        .map_or(key_map.hard_drop, |input| key_map.key_for(*input)),
+                );
+            }
+            None => self.target = None,
+        }
+        InputEvent::pressed(keys)
+    }
+}
+
+// empty cells a T piece pointing down would fill with three corners covered, the usual T-spin
+// double setup
+fn count_t_slots(collision_map: &CollisionMap) -> usize {
+    let size = collision_map.size();
+    let occupied = |row: isize, col: isize| collision_map.is_occupied(Position::new(row, col));
+    let mut slots = 0;
+    for row in 1..size.rows - 1 {
+        for col in 1..size.cols - 1 {
+            let t_fits = !occupied(row, col - 1)
+                && !occupied(row, col)
+                && !occupied(row, col + 1)
+                && !occupied(row + 1, col);
+            let corners_below = occupied(row + 1, col - 1) && occupied(row + 1, col + 1);
+            let overhang = occupied(row - 1, col - 1) || occupied(row - 1, col + 1);
+            if t_fits && corners_below && overhang {
+                slots += 1;
+            }
+        }
+    }
+    slots
+}
@@ -0,0 +1,272 @@
+use std::fmt;
+
+use crate::{Position, TetraminoKind};
+
+// rows of a fumen field above the garbage line, and how many cells it encodes with that line
+const FUMEN_ROWS: isize = 23;
+const FUMEN_COLS: isize = 10;
+const FUMEN_CELLS: usize = ((FUMEN_ROWS + 1) * FUMEN_COLS) as usize;
+const FUMEN_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// a filled cell of a diagram, None as kind for garbage; y counts up from the bottom row
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DiagramCell {
+    pub x: isize,
+    pub y: isize,
+    pub kind: Option<TetraminoKind>,
+}
+
+impl DiagramCell {
+    // where the cell lands on a playfield with this many rows
+    pub fn position(&self, rows: isize) -> Position {
+        Position::new(rows - 1 - self.y, self.x)
+    }
+}
+
+// a board written down in the fumen or plain text format, with the pieces to play on it
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Diagram {
+    pub cells: Vec<DiagramCell>,
+    pub queue: Vec<TetraminoKind>,
+    pub hold: Option<TetraminoKind>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DiagramError {
+    UnsupportedFumenVersion,
+    InvalidFumenCharacter(char),
+    TruncatedFumen,
+    InvalidCell { line: usize, character: char },
+    InvalidPiece(char),
+}
+
+impl fmt::Display for DiagramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagramError::UnsupportedFumenVersion => write!(f, "only v115 fumens are supported"),
+            DiagramError::InvalidFumenCharacter(c) => write!(f, "'{c}' can't appear in a fumen"),
+            DiagramError::TruncatedFumen => write!(f, "fumen ends before its field does"),
+            DiagramError::InvalidCell { line, character } => {
+                write!(f, "line {line}: '{character}' is not a cell")
+            }
+            DiagramError::InvalidPiece(c) => write!(f, "'{c}' is not a piece"),
+        }
+    }
+}
+
+impl std::error::Error for DiagramError {}
+
+pub fn piece_from_char(c: char) -> Option<TetraminoKind> {
+    match c.to_ascii_uppercase() {
+        'I' => Some(TetraminoKind::I),
+        'L' => Some(TetraminoKind::L),
+        'J' => Some(TetraminoKind::J),
+        'S' => Some(TetraminoKind::S),
+        'Z' => Some(TetraminoKind::Z),
+        'O' => Some(TetraminoKind::O),
+        'T' => Some(TetraminoKind::T),
+        _ => None,
+    }
+}
+
+pub fn piece_char(kind: TetraminoKind) -> char {
+    match kind {
+        TetraminoKind::I => 'I',
+        TetraminoKind::L => 'L',
+        TetraminoKind::J => 'J',
+        TetraminoKind::S => 'S',
+        TetraminoKind::Z => 'Z',
+        TetraminoKind::O => 'O',
+        TetraminoKind::T => 'T',
+    }
+}
+
+pub fn parse_queue(text: &str) -> Result<Vec<TetraminoKind>, DiagramError> {
+    text.chars()
+        .filter(|c| !c.is_whitespace() && *c != ',')
+        .map(|c| piece_from_char(c).ok_or(DiagramError::InvalidPiece(c)))
+        .collect()
+}
+
+impl Diagram {
+    // a fumen (or a link to one) or the text format, whichever the input looks like
+    pub fn parse(input: &str) -> Result<Diagram, DiagramError> {
+        let trimmed = input.trim();
+        match trimmed.find("v1") {
+            Some(start) if !trimmed[..start].contains('\n') && trimmed[start..].contains('@') => {
+                Diagram::from_fumen(&trimmed[start..])
+            }
+            _ => Diagram::from_text(input),
+        }
+    }
+
+    // rows top to bottom: a piece letter for its cells, G, X or # for garbage and . or _ for
+    // empty cells; a line starting with "queue:" gives the pieces to play, "hold:" the held one
+    pub fn from_text(text: &str) -> Result<Diagram, DiagramError> {
+        let mut diagram = Diagram::default();
+        let mut rows = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim_end();
+            if let Some(queue) = line.strip_prefix("queue:") {
+                diagram.queue = parse_queue(queue)?;
+            } else if let Some(hold) = line.strip_prefix("hold:") {
+                diagram.hold = parse_queue(hold)?.first().copied();
+            } else if !line.trim().is_empty() {
+                rows.push((number + 1, line));
+            }
+        }
+        for (y, (number, line)) in rows.iter().rev().enumerate() {
+            for (x, character) in line.chars().enumerate() {
+                let kind = match character {
+                    '.' | '_' | ' ' => continue,
+                    'G' | 'g' | 'X' | 'x' | '#' => None,
+                    c => Some(piece_from_char(c).ok_or(DiagramError::InvalidCell {
+                        line: *number,
+                        character: c,
+                    })?),
+                };
+                diagram.cells.push(DiagramCell {
+                    x: x as isize,
+                    y: y as isize,
+                    kind,
+                });
+            }
+        }
+        Ok(diagram)
+    }
+
+    pub fn to_text(&self) -> String {
+        let width = self
+            .cells
+            .iter()
+            .map(|cell| cell.x + 1)
+            .max()
+            .unwrap_or(0)
+            .max(10);
+        let height = self.cells.iter().map(|cell| cell.y + 1).max().unwrap_or(0);
+        let mut text = String::new();
+        if !self.queue.is_empty() {
+            text.push_str("queue: ");
+            text.extend(self.queue.iter().map(|kind| piece_char(*kind)));
+            text.push('\n');
+        }
+        if let Some(hold) = self.hold {
+            text.push_str(&format!("hold: {}\n", piece_char(hold)));
+        }
+        for y in (0..height).rev() {
+            for x in 0..width {
+                let cell = self.cells.iter().find(|cell| cell.x == x && cell.y == y);
+                text.push(match cell {
+                    Some(DiagramCell {
+                        kind: Some(kind), ..
+                    }) => piece_char(*kind),
+                    Some(DiagramCell { kind: None, .. }) => 'G',
+                    None => '.',
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    // a single page v115 fumen of the field, cells above the fumen field are left out
+    pub fn to_fumen(&self) -> String {
+        let mut field = [0usize; FUMEN_CELLS];
+        for cell in &self.cells {
+            let row = FUMEN_ROWS - 1 - cell.y;
+            if (0..FUMEN_ROWS).contains(&row) && (0..FUMEN_COLS).contains(&cell.x) {
+                field[(row * FUMEN_COLS + cell.x) as usize] = match cell.kind {
+                    Some(TetraminoKind::I) => 1,
+                    Some(TetraminoKind::L) => 2,
+                    Some(TetraminoKind::O) => 3,
+                    Some(TetraminoKind::Z) => 4,
+                    Some(TetraminoKind::T) => 5,
+                    Some(TetraminoKind::J) => 6,
+                    Some(TetraminoKind::S) => 7,
+                    None => 8,
+                };
+            }
+        }
+        let mut fumen = String::from("v115@");
+        let mut push = |value: usize, digits: u32| {
+            for digit in 0..digits {
+                fumen.push(FUMEN_ALPHABET[value / 64usize.pow(digit) % 64] as char);
+            }
+        };
+        let mut runs = Vec::new();
+        for value in field {
+            match runs.last_mut() {
+                Some((last, count)) if *last == value => *count += 1,
+                _ => runs.push((value, 1)),
+            }
+        }
+        for (value, count) in &runs {
+            push((value + 8) * FUMEN_CELLS + count - 1, 2);
+        }
+        // an empty field is followed by how many pages repeat it
+        if runs == [(0, FUMEN_CELLS)] {
+            push(0, 1);
+        }
+        // no piece on the page, guideline colors
+        push(30720, 3);
+        fumen
+    }
+
+    // the field of the first page of a v115 fumen; pieces and comments of pages are not read
+    pub fn from_fumen(fumen: &str) -> Result<Diagram, DiagramError> {
+        let data = fumen
+            .trim()
+            .strip_prefix("v115@")
+            .ok_or(DiagramError::UnsupportedFumenVersion)?;
+        let mut values = data.chars().filter(|c| *c != '?').map(|c| {
+            FUMEN_ALPHABET
+                .iter()
+                .position(|a| *a as char == c)
+                .ok_or(DiagramError::InvalidFumenCharacter(c))
+        });
+        let mut poll = |digits: u32| -> Result<usize, DiagramError> {
+            (0..digits).try_fold(0, |value, digit| {
+                let next = values.next().ok_or(DiagramError::TruncatedFumen)??;
+                Ok(value + next * 64usize.pow(digit))
+            })
+        };
+
+        // runs of (cell difference + 8, length - 1) against an empty field
+        let mut field = [0usize; FUMEN_CELLS];
+        let mut index = 0;
+        while index < FUMEN_CELLS {
+            let run = poll(2)?;
+            let value = (run / FUMEN_CELLS).saturating_sub(8);
+            let count = run % FUMEN_CELLS + 1;
+            for cell in field.iter_mut().skip(index).take(count) {
+                *cell = value;
+            }
+            index += count;
+        }
+
+        let mut diagram = Diagram::default();
+        for (index, value) in field.iter().enumerate() {
+            let row = index as isize / FUMEN_COLS;
+            // the last row is the garbage line under the field
+            if *value == 0 || row >= FUMEN_ROWS {
+                continue;
+            }
+            let kind = match value {
+                1 => Some(TetraminoKind::I),
+                2 => Some(TetraminoKind::L),
+                3 => Some(TetraminoKind::O),
+                4 => Some(TetraminoKind::Z),
+                5 => Some(TetraminoKind::T),
+                6 => Some(TetraminoKind::J),
+                7 => Some(TetraminoKind::S),
+                _ => None,
+            };
+            diagram.cells.push(DiagramCell {
+                x: index as isize % FUMEN_COLS,
+                y: FUMEN_ROWS - 1 - row,
+                kind,
+            });
+        }
+        Ok(diagram)
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    Block, GARBAGE_COLOR, GameState, PlayfieldSize, Position, TetraminoKind,
+    diagram::{Diagram, DiagramCell},
+};
+
+// a board being drawn by hand, with the queue and hold to play it with
+#[derive(Clone, Debug)]
+pub struct BoardEditor {
+    size: PlayfieldSize,
+    // None as kind for garbage
+    cells: HashMap<Position, Option<TetraminoKind>>,
+    brush: Option<TetraminoKind>,
+    queue: Vec<TetraminoKind>,
+    hold: Option<TetraminoKind>,
+}
+
+impl BoardEditor {
+    pub fn new(size: PlayfieldSize) -> BoardEditor {
+        BoardEditor {
+            size,
+            cells: HashMap::new(),
+            brush: None,
+            queue: Vec::new(),
+            hold: None,
+        }
+    }
+
+    // cells outside the board are dropped
+    pub fn from_diagram(size: PlayfieldSize, diagram: &Diagram) -> BoardEditor {
+        let mut editor = BoardEditor::new(size);
+        editor.load(diagram);
+        editor
+    }
+
+    pub fn load(&mut self, diagram: &Diagram) {
+        self.cells = diagram
+            .cells
+            .iter()
+            .map(|cell| (cell.position(self.size.rows), cell.kind))
+            .filter(|(position, _)| position.is_inbound(self.size.rows, self.size.cols))
+            .collect();
+        self.queue = diagram.queue.clone();
+        self.hold = diagram.hold;
+    }
+
+    pub fn to_diagram(&self) -> Diagram {
+        let mut cells: Vec<DiagramCell> = self
+            .cells
+            .iter()
+            .map(|(position, kind)| DiagramCell {
+                x: position.col,
+                y: self.size.rows - 1 - position.row,
+                kind: *kind,
+            })
+            .collect();
+        cells.sort_by_key(|cell| (cell.y, cell.x));
+        Diagram {
+            cells,
+            queue: self.queue.clone(),
+            hold: self.hold,
+        }
+    }
+
+    // the game the board describes, ready to be played from its first piece
+    pub fn start(&self, game_state: GameState) -> GameState {
+        let game_state = if self.queue.is_empty() {
+            game_state
+        } else {
+            game_state.with_queue(&self.queue)
+        };
+        let mut game_state = game_state.with_hold(self.hold);
+        let blocks: HashSet<Block> = self
+            .cells
+            .iter()
+            .map(|(position, kind)| Block {
+                color: kind.map_or(GARBAGE_COLOR, TetraminoKind::color),
+                coordinates: *position,
+            })
+            .collect();
+        game_state.place_blocks(&blocks);
+        game_state
+    }
+
+    pub fn paint(&mut self, position: Position) {
+        if position.is_inbound(self.size.rows, self.size.cols) {
+            self.cells.insert(position, self.brush);
+        }
+    }
+    pub fn erase(&mut self, position: Position) {
+        self.cells.remove(&position);
+    }
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    pub fn size(&self) -> PlayfieldSize {
+        self.size
+    }
+    // filled cells and their kind, None for garbage
+    pub fn cells(&self) -> impl Iterator<Item = (&Position, &Option<TetraminoKind>)> {
+        self.cells.iter()
+    }
+    // what painting puts down, None for garbage
+    pub fn brush(&self) -> Option<TetraminoKind> {
+        self.brush
+    }
+    pub fn set_brush(&mut self, brush: Option<TetraminoKind>) {
+        self.brush = brush;
+    }
+    pub fn queue(&self) -> &[TetraminoKind] {
+        &self.queue
+    }
+    pub fn push_queue(&mut self, kind: TetraminoKind) {
+        self.queue.push(kind);
+    }
+    pub fn pop_queue(&mut self) -> Option<TetraminoKind> {
+        self.queue.pop()
+    }
+    pub fn hold(&self) -> Option<TetraminoKind> {
+        self.hold
+    }
+    pub fn set_hold(&mut self, hold: Option<TetraminoKind>) {
+        self.hold = hold;
+    }
+}
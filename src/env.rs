@@ -0,0 +1,203 @@
+use strum::IntoEnumIterator;
+
+use crate::{
+    GameState, PlayfieldSize, RotationState, TetraminoKind,
+    move_generator::{CollisionMap, Placement, generate_placements_from},
+    ruleset::{Ruleset, RulesetError},
+};
+
+// length of the preview in an observation, slots past the ruleset's preview are empty
+pub const PREVIEW_SLOTS: usize = 6;
+
+// every action is a piece dropped in one of the four rotations with its leftmost cell in a
+// column, optionally after holding: index = (hold * 4 + rotation) * cols + column
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Action {
+    pub hold: bool,
+    pub rotation_state: RotationState,
+    pub column: isize,
+}
+
+impl Action {
+    pub fn from_index(index: usize, cols: isize) -> Option<Action> {
+        let cols = cols as usize;
+        if index >= 8 * cols {
+            return None;
+        }
+        Some(Action {
+            hold: index / (4 * cols) == 1,
+            rotation_state: RotationState::iter().nth(index / cols % 4)?,
+            column: (index % cols) as isize,
+        })
+    }
+
+    pub fn index(&self, cols: isize) -> usize {
+        let rotation = RotationState::iter()
+            .position(|state| state == self.rotation_state)
+            .unwrap_or_default();
+        ((self.hold as usize * 4 + rotation) * cols as usize) + self.column as usize
+    }
+}
+
+pub fn action_count(cols: isize) -> usize {
+    8 * cols as usize
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Observation {
+    pub rows: isize,
+    pub cols: isize,
+    // row major, 1 for a filled cell
+    pub board: Vec<u8>,
+    pub current: TetraminoKind,
+    pub preview: [Option<TetraminoKind>; PREVIEW_SLOTS],
+    pub hold: Option<TetraminoKind>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Step {
+    pub reward: f32,
+    pub done: bool,
+    // the action had no reachable placement, nothing was played
+    pub invalid: bool,
+}
+
+// a Gym style environment: one step places one piece, the reward is the lines it cleared
+#[derive(Clone)]
+pub struct Env {
+    size: PlayfieldSize,
+    ruleset: Ruleset,
+    game_state: GameState,
+}
+
+impl Env {
+    pub fn new(size: PlayfieldSize, seed: u64) -> Result<Env, RulesetError> {
+        Env::from_ruleset(Ruleset::with_size(size), seed)
+    }
+
+    // rules no game can be played by, or with more preview than an observation holds, are
+    // refused
+    pub fn from_ruleset(ruleset: Ruleset, seed: u64) -> Result<Env, RulesetError> {
+        ruleset.validate()?;
+        if ruleset.preview > PREVIEW_SLOTS {
+            return Err(RulesetError::Invalid(format!(
+                "preview is {}, an observation holds at most {PREVIEW_SLOTS}",
+                ruleset.preview
+            )));
+        }
+        Ok(Env {
+            size: ruleset.size(),
+            game_state: ruleset.new_game().with_seed(seed),
+            ruleset,
+        })
+    }
+
+    pub fn game_state(&self) -> &GameState {
+        &self.game_state
+    }
+    pub fn action_count(&self) -> usize {
+        action_count(self.size.cols)
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game_state = self.ruleset.new_game().with_seed(seed);
+        self.observe()
+    }
+
+    pub fn observe(&self) -> Observation {
+        let PlayfieldSize { rows, cols } = self.size;
+        let mut board = vec![0; (rows * cols) as usize];
+        for block in self.game_state.placed_blocks().get_blocks() {
+            let position = block.coordinates;
+            if position.is_inbound(rows, cols) {
+                board[(position.row * cols + position.col) as usize] = 1;
+            }
+        }
+        let mut preview = [None; PREVIEW_SLOTS];
+        for (slot, kind) in preview.iter_mut().zip(self.game_state.next_queue()) {
+            *slot = Some(kind);
+        }
+        Observation {
+            rows,
+            cols,
+            board,
+            current: self.game_state.current_tetramino().kind(),
+            preview,
+            hold: self.game_state.hold_kind(),
+        }
+    }
+
+    // the placement an action stands for: the shortest path to a resting position with the
+    // requested rotation and leftmost column, which is a straight drop whenever there is one
+    fn placement(&self, action: Action) -> Option<Placement> {
+        let game_state = &self.game_state;
+        let (kind, rotation_state, offset) = if action.hold {
+            if !game_state.can_hold() {
+                return None;
+            }
+            let kind = game_state.hold_kind().unwrap_or(game_state.next_kind());
+            (
+                kind,
+                RotationState::Init,
+                game_state.playfield().spawn_offset(),
+            )
+        } else {
+            let active = game_state.current_tetramino();
+            (active.kind(), active.rotation_state(), active.offset())
+        };
+        generate_placements_from(
+            &CollisionMap::new(game_state.playfield()),
+            &game_state.shape_table(kind),
+            rotation_state,
+            offset,
+        )
+        .into_iter()
+        .filter(|placement| {
+            placement.rotation_state == action.rotation_state
+                && placement.blocks.iter().map(|b| b.coordinates.col).min() == Some(action.column)
+        })
+        .min_by_key(|placement| placement.inputs.len())
+    }
+
+    // which action indices currently lead somewhere
+    pub fn action_mask(&self) -> Vec<bool> {
+        (0..self.action_count())
+            .map(|index| {
+                !self.game_state.is_game_over()
+                    && Action::from_index(index, self.size.cols)
+                        .and_then(|action| self.placement(action))
+                        .is_some()
+            })
+            .collect()
+    }
+
+    pub fn step(&mut self, index: usize) -> Step {
+        let invalid = Step {
+            reward: 0.,
+            done: self.game_state.is_game_over(),
+            invalid: true,
+        };
+        if self.game_state.is_game_over() {
+            return invalid;
+        }
+        let Some((action, placement)) = Action::from_index(index, self.size.cols)
+            .and_then(|action| Some((action, self.placement(action)?)))
+        else {
+            return invalid;
+        };
+
+        let lines_before = self.game_state.lines_cleared();
+        if action.hold {
+            self.game_state.hold();
+        }
+        for input in placement.inputs_before_hard_drop() {
+            self.game_state.apply_input(*input);
+        }
+        self.game_state.hard_drop();
+        Step {
+            reward: (self.game_state.lines_cleared() - lines_before) as f32,
+            done: self.game_state.is_game_over(),
+            invalid: false,
+        }
+    }
+}
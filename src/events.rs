@@ -0,0 +1,72 @@
+use crate::{Block, LockRecord, Position, RotationDirection, TetraminoKind, scoring::SpinKind};
+
+// what the engine did, in the order it happened; a game built with_events keeps them until
+// take_events, so frontends, stats and the like don't have to compare states to find out
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GameEvent {
+    // a piece came into play at the spawn position
+    PieceSpawned {
+        kind: TetraminoKind,
+    },
+    // the piece in play was moved by the player, gravity and drops aren't moves
+    Moved {
+        offset: Position,
+    },
+    // kick_index is the kick test that let the rotation through, 0 when it turned in place
+    Rotated {
+        direction: RotationDirection,
+        kick_index: usize,
+    },
+    SoftDropped {
+        rows: usize,
+    },
+    // cells are where the piece landed, before it locks
+    HardDropped {
+        rows: usize,
+        cells: Vec<Position>,
+    },
+    Locked {
+        kind: TetraminoKind,
+        cells: Vec<Position>,
+        spin: SpinKind,
+    },
+    // rows are numbered as they were before clearing and cells are what they held, `garbage` of
+    // them were garbage rows; they stay on the board for delay_ms, 0 when they went at once.
+    // combo counts the clears before this one and attack includes what cancelled garbage
+    LinesCleared {
+        rows: Vec<isize>,
+        cells: Vec<Block>,
+        delay_ms: usize,
+        spin: SpinKind,
+        garbage: usize,
+        back_to_back: bool,
+        combo: Option<usize>,
+        perfect_clear: bool,
+        attack: usize,
+    },
+    // kind went into hold
+    Hold {
+        kind: TetraminoKind,
+    },
+    // lines of garbage queued up from an opponent
+    GarbageReceived {
+        lines: usize,
+    },
+    LevelUp {
+        level: usize,
+    },
+    // closes every lock, after its Locked and LinesCleared; net play and replays rebuild the
+    // game from these
+    LockRecorded {
+        record: LockRecord,
+    },
+    GameOver,
+}
+
+// the lock records among `events`, in order
+pub fn lock_records(events: &[GameEvent]) -> impl Iterator<Item = LockRecord> + '_ {
+    events.iter().filter_map(|event| match event {
+        GameEvent::LockRecorded { record } => Some(record.clone()),
+        _ => None,
+    })
+}
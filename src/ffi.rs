@@ -0,0 +1,137 @@
+// C ABI over the Gym style environment; every function takes the pointer tetrs_env_new gave
+// out. Piece kinds are passed as their index in I, L, J, S, Z, O, T order and -1 for none.
+// include/tetrs.h declares all of it for C callers.
+
+use crate::{
+    PlayfieldSize, TetraminoKind,
+    env::{Env, PREVIEW_SLOTS, Step},
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TetrsStep {
+    pub reward: f32,
+    pub done: bool,
+    pub invalid: bool,
+}
+
+impl From<Step> for TetrsStep {
+    fn from(value: Step) -> Self {
+        TetrsStep {
+            reward: value.reward,
+            done: value.done,
+            invalid: value.invalid,
+        }
+    }
+}
+
+// the pieces of an observation, -1 in the preview slots the ruleset doesn't show
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TetrsQueue {
+    pub current: i8,
+    pub preview: [i8; PREVIEW_SLOTS],
+    pub hold: i8,
+}
+
+fn kind_index(kind: Option<TetraminoKind>) -> i8 {
+    kind.map_or(-1, |kind| kind as i8)
+}
+
+/// A new environment on a board of `rows` by `cols`, or null when no game fits that size:
+/// it needs at least 4 of each and at most 1000.
+#[unsafe(no_mangle)]
+pub extern "C" fn tetrs_env_new(rows: i64, cols: i64, seed: u64) -> *mut Env {
+    let (Ok(rows), Ok(cols)) = (isize::try_from(rows), isize::try_from(cols)) else {
+        return std::ptr::null_mut();
+    };
+    match Env::new(PlayfieldSize { rows, cols }, seed) {
+        Ok(env) => Box::into_raw(Box::new(env)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// `env` must come from `tetrs_env_new` or `tetrs_env_clone` and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tetrs_env_free(env: *mut Env) {
+    if !env.is_null() {
+        drop(unsafe { Box::from_raw(env) });
+    }
+}
+
+/// # Safety
+/// `env` must be a live environment.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tetrs_env_clone(env: *const Env) -> *mut Env {
+    let env = unsafe { &*env };
+    Box::into_raw(Box::new(env.clone()))
+}
+
+/// # Safety
+/// `env` must be a live environment.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tetrs_env_reset(env: *mut Env, seed: u64) {
+    unsafe { &mut *env }.reset(seed);
+}
+
+/// # Safety
+/// `env` must be a live environment.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tetrs_env_action_count(env: *const Env) -> usize {
+    unsafe { &*env }.action_count()
+}
+
+/// # Safety
+/// `env` must be a live environment.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tetrs_env_step(env: *mut Env, action: usize) -> TetrsStep {
+    unsafe { &mut *env }.step(action).into()
+}
+
+/// Writes rows * cols cells, row major with 1 for filled, and the current piece, the preview
+/// and the held piece to `queue`. Returns how many cells were written, at most `board_len`.
+///
+/// # Safety
+/// `env` must be a live environment, `board` must hold `board_len` bytes and `queue` must
+/// point to a `TetrsQueue`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tetrs_env_observe(
+    env: *const Env,
+    board: *mut u8,
+    board_len: usize,
+    queue: *mut TetrsQueue,
+) -> usize {
+    let observation = unsafe { &*env }.observe();
+    let written = observation.board.len().min(board_len);
+    let pieces = TetrsQueue {
+        current: kind_index(Some(observation.current)),
+        preview: observation.preview.map(kind_index),
+        hold: kind_index(observation.hold),
+    };
+    unsafe {
+        std::ptr::copy_nonoverlapping(observation.board.as_ptr(), board, written);
+        queue.write(pieces);
+    }
+    written
+}
+
+/// Writes 1 for every action that can be played right now. Returns how many were written.
+///
+/// # Safety
+/// `env` must be a live environment and `mask` must hold `mask_len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tetrs_env_action_mask(
+    env: *const Env,
+    mask: *mut u8,
+    mask_len: usize,
+) -> usize {
+    let actions: Vec<u8> = unsafe { &*env }
+        .action_mask()
+        .into_iter()
+        .map(u8::from)
+        .collect();
+    let written = actions.len().min(mask_len);
+    unsafe { std::ptr::copy_nonoverlapping(actions.as_ptr(), mask, written) };
+    written
+}
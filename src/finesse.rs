@@ -0,0 +1,204 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::{
+    Block, Playfield, Position, RotationState, TetraminoKind,
+    move_generator::{CollisionMap, Input, ShapeTable, drop_offset},
+    ruleset::RotationSystem,
+};
+
+// a single key press; DAS variants are a held shift that carries the piece to the wall
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter)]
+pub enum FinesseInput {
+    Left,
+    Right,
+    DasLeft,
+    DasRight,
+    RotateClockwise,
+    RotateCounterClockwise,
+    SoftDrop,
+}
+
+impl From<Input> for FinesseInput {
+    fn from(value: Input) -> Self {
+        match value {
+            Input::Left => FinesseInput::Left,
+            Input::Right => FinesseInput::Right,
+            Input::RotateClockwise => FinesseInput::RotateClockwise,
+            Input::RotateCounterClockwise => FinesseInput::RotateCounterClockwise,
+            Input::SoftDrop => FinesseInput::SoftDrop,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FinesseResult {
+    pub kind: TetraminoKind,
+    pub used: Vec<FinesseInput>,
+    pub optimal: Vec<FinesseInput>,
+}
+
+impl FinesseResult {
+    pub fn is_fault(&self) -> bool {
+        self.used.len() > self.optimal.len()
+    }
+    pub fn extra_presses(&self) -> usize {
+        self.used.len().saturating_sub(self.optimal.len())
+    }
+}
+
+fn apply(
+    collision_map: &CollisionMap,
+    shapes: &ShapeTable,
+    rotation_state: RotationState,
+    offset: Position,
+    input: FinesseInput,
+) -> Option<(RotationState, Position)> {
+    let single = |input| shapes.apply(collision_map, rotation_state, offset, input);
+    let held = |input| {
+        let mut state = shapes.apply(collision_map, rotation_state, offset, input)?;
+        while let Some(next) = shapes.apply(collision_map, state.0, state.1, input) {
+            state = next;
+        }
+        Some(state)
+    };
+    match input {
+        FinesseInput::Left => single(Input::Left),
+        FinesseInput::Right => single(Input::Right),
+        FinesseInput::DasLeft => held(Input::Left),
+        FinesseInput::DasRight => held(Input::Right),
+        FinesseInput::RotateClockwise => single(Input::RotateClockwise),
+        FinesseInput::RotateCounterClockwise => single(Input::RotateCounterClockwise),
+        FinesseInput::SoftDrop => single(Input::SoftDrop),
+    }
+}
+
+fn sorted_cells(cells: impl Iterator<Item = Position>) -> Vec<(isize, isize)> {
+    let mut cells: Vec<(isize, isize)> = cells.map(|cell| (cell.row, cell.col)).collect();
+    cells.sort();
+    cells
+}
+
+// fewest presses from spawn after which a hard drop locks the piece on the target cells
+pub fn optimal_inputs(
+    playfield: &Playfield,
+    rotation: RotationSystem,
+    kind: TetraminoKind,
+    target: &HashSet<Block>,
+) -> Option<Vec<FinesseInput>> {
+    let collision_map = CollisionMap::new(playfield);
+    let shapes = ShapeTable::new(kind).with_rotation_system(rotation);
+    let target = sorted_cells(target.iter().map(|b| b.coordinates));
+
+    let start = (RotationState::Init, playfield.spawn_offset());
+    if !collision_map.fits(shapes.cells(start.0), start.1) {
+        return None;
+    }
+    let mut parents: HashMap<(RotationState, Position), ((RotationState, Position), FinesseInput)> =
+        HashMap::new();
+    let mut visited = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(node) = queue.pop_front() {
+        let (rotation_state, offset) = node;
+        let cells = shapes.cells(rotation_state);
+        let landed = drop_offset(&collision_map, cells, offset);
+        if sorted_cells(cells.iter().map(|cell| *cell + landed)) == target {
+            let mut inputs = Vec::new();
+            let mut current = node;
+            while let Some((parent, input)) = parents.get(&current) {
+                inputs.push(*input);
+                current = *parent;
+            }
+            inputs.reverse();
+            return Some(inputs);
+        }
+        for input in FinesseInput::iter() {
+            if let Some(next) = apply(&collision_map, &shapes, rotation_state, offset, input)
+                && visited.insert(next)
+            {
+                parents.insert(next, (node, input));
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+pub fn analyze(
+    playfield: &Playfield,
+    rotation: RotationSystem,
+    kind: TetraminoKind,
+    target: &HashSet<Block>,
+    used: &[FinesseInput],
+) -> Option<FinesseResult> {
+    optimal_inputs(playfield, rotation, kind, target).map(|optimal| FinesseResult {
+        kind,
+        used: used.to_vec(),
+        optimal,
+    })
+}
+
+// collects the presses of the active piece and judges them when it locks
+#[derive(Clone, Default)]
+pub struct FinesseTracker {
+    inputs: Vec<FinesseInput>,
+    faults: usize,
+    pieces: usize,
+    last: Option<FinesseResult>,
+}
+
+impl FinesseTracker {
+    pub fn record(&mut self, input: FinesseInput) {
+        self.inputs.push(input);
+    }
+
+    // DAS charging turns the tap that started it into a held shift, it is still one press
+    pub fn record_auto_shift(&mut self, input: FinesseInput) {
+        let tap = match input {
+            FinesseInput::DasLeft => FinesseInput::Left,
+            FinesseInput::DasRight => FinesseInput::Right,
+            _ => input,
+        };
+        match self.inputs.iter().rposition(|recorded| *recorded == tap) {
+            Some(index) => self.inputs[index] = input,
+            None => self.inputs.push(input),
+        }
+    }
+
+    pub fn reset_piece(&mut self) {
+        self.inputs.clear();
+    }
+
+    pub fn lock(
+        &mut self,
+        playfield: &Playfield,
+        rotation: RotationSystem,
+        kind: TetraminoKind,
+        blocks: &HashSet<Block>,
+    ) {
+        let inputs = std::mem::take(&mut self.inputs);
+        if let Some(result) = analyze(playfield, rotation, kind, blocks, &inputs) {
+            self.pieces += 1;
+            if result.is_fault() {
+                self.faults += 1;
+            }
+            self.last = Some(result);
+        }
+    }
+
+    pub fn faults(&self) -> usize {
+        self.faults
+    }
+    pub fn pieces(&self) -> usize {
+        self.pieces
+    }
+    pub fn last(&self) -> Option<&FinesseResult> {
+        self.last.as_ref()
+    }
+    pub fn current_inputs(&self) -> &[FinesseInput] {
+        &self.inputs
+    }
+}
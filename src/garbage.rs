@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    TimerMs,
+    scoring::{LineClear, SpinKind},
+};
+
+// lines of garbage sent for a clear, indexed by lines cleared or combo count
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttackTable {
+    pub lines: [usize; 5],
+    pub t_spin: [usize; 4],
+    pub t_spin_mini: [usize; 3],
+    pub back_to_back_bonus: usize,
+    // the last entry keeps applying to longer combos
+    pub combo: Vec<usize>,
+    pub perfect_clear: usize,
+}
+
+impl Default for AttackTable {
+    fn default() -> Self {
+        AttackTable {
+            lines: [0, 0, 1, 2, 4],
+            t_spin: [0, 2, 4, 6],
+            t_spin_mini: [0, 0, 1],
+            back_to_back_bonus: 1,
+            combo: vec![0, 0, 1, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5],
+            perfect_clear: 10,
+        }
+    }
+}
+
+impl AttackTable {
+    pub fn attack(&self, clear: &LineClear) -> usize {
+        if clear.lines == 0 {
+            return 0;
+        }
+        let lookup = |table: &[usize], index: usize| {
+            table
+                .get(index)
+                .or(table.last())
+                .copied()
+                .unwrap_or_default()
+        };
+        let mut attack = match clear.spin {
+            SpinKind::None => lookup(&self.lines, clear.lines),
+            SpinKind::Mini => lookup(&self.t_spin_mini, clear.lines),
+            SpinKind::Full => lookup(&self.t_spin, clear.lines),
+        };
+        if clear.back_to_back {
+            attack += self.back_to_back_bonus;
+        }
+        if let Some(combo) = clear.combo {
+            attack += lookup(&self.combo, combo);
+        }
+        if clear.perfect_clear {
+            attack += self.perfect_clear;
+        }
+        attack
+    }
+}
+
+pub const DEFAULT_GARBAGE_DELAY_MS: usize = 500;
+
+#[derive(Clone, Copy)]
+struct IncomingGarbage {
+    lines: usize,
+    hole: isize,
+    delay: TimerMs,
+    ready: bool,
+}
+
+// garbage waiting to enter the playfield; every batch keeps a single hole column
+#[derive(Clone)]
+pub struct GarbageQueue {
+    incoming: VecDeque<IncomingGarbage>,
+    delay_ms: usize,
+    rng: StdRng,
+}
+
+impl GarbageQueue {
+    // holes come from their own stream so they don't shift the piece sequence of the same seed
+    pub fn new(delay_ms: usize, seed: u64) -> GarbageQueue {
+        GarbageQueue {
+            incoming: VecDeque::new(),
+            delay_ms,
+            rng: StdRng::seed_from_u64(seed ^ 0x9e37_79b9_7f4a_7c15),
+        }
+    }
+
+    pub fn delay_ms(&self) -> usize {
+        self.delay_ms
+    }
+    pub fn set_delay(&mut self, delay_ms: usize) {
+        self.delay_ms = delay_ms;
+    }
+
+    pub fn receive(&mut self, lines: usize, cols: isize) {
+        if lines == 0 {
+            return;
+        }
+        self.incoming.push_back(IncomingGarbage {
+            lines,
+            hole: self.rng.random_range(0..cols.max(1) as i64) as isize,
+            delay: TimerMs::new(self.delay_ms),
+            ready: self.delay_ms == 0,
+        });
+    }
+
+    // an attack first cancels garbage still waiting, oldest first; what is left gets sent
+    pub fn cancel(&mut self, mut attack: usize) -> usize {
+        while attack > 0
+            && let Some(front) = self.incoming.front_mut()
+        {
+            let cancelled = attack.min(front.lines);
+            front.lines -= cancelled;
+            attack -= cancelled;
+            if front.lines == 0 {
+                self.incoming.pop_front();
+            }
+        }
+        attack
+    }
+
+    pub fn restart_timers(&mut self) {
+        for garbage in &mut self.incoming {
+            garbage.delay = garbage.delay.reset();
+        }
+    }
+
+    // the seeded stream garbage holes come from, for modes that make up their own garbage
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    pub fn total(&self) -> usize {
+        self.incoming.iter().map(|garbage| garbage.lines).sum()
+    }
+
+    // lines whose delay has run out, these go in on the next lock without a clear
+    pub fn ready(&mut self) -> usize {
+        self.incoming
+            .iter_mut()
+            .filter_map(|garbage| {
+                garbage.ready = garbage.ready || garbage.delay.update();
+                garbage.ready.then_some(garbage.lines)
+            })
+            .sum()
+    }
+
+    // removes the ready batches and returns the hole of every row to insert
+    pub fn take_ready(&mut self) -> Vec<isize> {
+        self.ready();
+        let mut holes = Vec::new();
+        while let Some(front) = self.incoming.front()
+            && front.ready
+        {
+            holes.extend(std::iter::repeat_n(front.hole, front.lines));
+            self.incoming.pop_front();
+        }
+        holes
+    }
+}
@@ -1,24 +1,71 @@
 use std::{
-    collections::HashSet,
+    collections::{HashSet, VecDeque},
     hash::Hash,
     ops::{Add, AddAssign, RemAssign, Sub},
     time::Instant,
 };
 
 use macroquad::{
-    color::{Color, RED},
+    color::{Color, LIGHTGRAY, RED},
     input::KeyCode,
 };
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-use crate::tetramino_shape::{RotationDirection, RotationResult, Tetramino, TetraminoKind};
+use crate::events::GameEvent;
+use crate::finesse::{FinesseInput, FinesseTracker};
+use crate::garbage::{AttackTable, GarbageQueue};
+use crate::move_generator::{Input, ShapeTable};
+use crate::ruleset::{GravityCurve, LockDelay, LockReset, Randomizer, Ruleset};
+use crate::scoring::{DEFAULT_LINES_PER_LEVEL, Scoring, SpinKind};
+use crate::settings::Handling;
+pub use crate::tetramino_shape::{RotationDirection, RotationState, TetraminoKind};
+use crate::tetramino_shape::{RotationResult, Tetramino};
 
+pub mod animation;
+pub mod audio;
+pub mod bot;
+pub mod diagram;
+pub mod editor;
+pub mod env;
+pub mod events;
+pub mod ffi;
+pub mod finesse;
+pub mod garbage;
+pub mod live_stats;
+pub mod modes;
+pub mod move_generator;
+pub mod net;
+pub mod pc_solver;
+#[cfg(feature = "python")]
+mod python;
+pub mod records;
+pub mod ruleset;
+pub mod scoring;
+pub mod settings;
+pub mod spectate;
+pub mod storage;
+pub mod tbp;
 mod tetramino_shape;
-#[derive(Debug)]
+pub mod versus;
+
+pub const GARBAGE_COLOR: Color = LIGHTGRAY;
+#[derive(Clone, Debug)]
 pub struct InputEvent {
     pub keys: HashSet<KeyCode>,
+    pub held: HashSet<KeyCode>,
+}
+
+impl InputEvent {
+    pub fn pressed(keys: HashSet<KeyCode>) -> InputEvent {
+        InputEvent {
+            held: keys.clone(),
+            keys,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -50,7 +97,7 @@ impl Hash for Block {
     }
 }
 
-#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Position {
     pub row: isize,
     pub col: isize,
@@ -104,6 +151,7 @@ impl Sub<Position> for Position {
     }
 }
 
+#[derive(Clone)]
 pub struct ActiveTetramino {
     shape: Tetramino,
     offset: Position,
@@ -128,6 +176,18 @@ impl ActiveTetramino {
         self.offset += offset;
     }
 
+    pub fn kind(&self) -> TetraminoKind {
+        self.shape.kind()
+    }
+
+    pub fn rotation_state(&self) -> RotationState {
+        self.shape.rotation_state()
+    }
+
+    pub fn offset(&self) -> Position {
+        self.offset
+    }
+
     fn get_rotation_result(&self, direction: RotationDirection) -> RotationResult {
         self.shape.get_rotated_and_offsets(direction)
     }
@@ -144,8 +204,11 @@ impl ActiveTetramino {
     }
 }
 
-struct Playfield {
+#[derive(Clone)]
+pub struct Playfield {
     size: PlayfieldSize,
+    // rows at the top that are out of sight, counted in size
+    hidden_rows: isize,
     placed_blocks: PlacedBlocks,
 }
 
@@ -153,16 +216,66 @@ impl Playfield {
     pub fn new(size: PlayfieldSize) -> Playfield {
         Playfield {
             size,
+            hidden_rows: 0,
             placed_blocks: PlacedBlocks::default(),
         }
     }
+    pub fn with_hidden_rows(mut self, hidden_rows: isize) -> Playfield {
+        self.hidden_rows = hidden_rows.clamp(0, self.size.rows);
+        self
+    }
     pub fn put_blocks(&mut self, blocks: &HashSet<Block>) {
         self.placed_blocks.put_blocks(blocks);
     }
-    fn check_intersections(&self, blocks: &HashSet<Block>) -> bool {
+    pub fn size(&self) -> PlayfieldSize {
+        self.size
+    }
+    pub fn placed_blocks(&self) -> &PlacedBlocks {
+        &self.placed_blocks
+    }
+    pub fn hidden_rows(&self) -> isize {
+        self.hidden_rows
+    }
+    // pieces appear in the two rows right above the visible ones, or at the very top when
+    // fewer rows are hidden, left of center on boards of even width
+    pub fn spawn_offset(&self) -> Position {
+        Position::new((self.hidden_rows - 2).max(0), (self.size.cols - 3) / 2)
+    }
+    // the rows cleared, top first, and how many of them were garbage
+    pub fn clear_full_rows(&mut self) -> (Vec<isize>, usize) {
+        let (full_rows, garbage) = self.full_rows();
+        self.placed_blocks.clear_rows(&full_rows);
+        (full_rows, garbage)
+    }
+    // the rows that are full, top first, and how many of them are garbage; they stay put
+    pub fn full_rows(&self) -> (Vec<isize>, usize) {
+        let full_rows = self.placed_blocks.full_rows(self.size);
+        let garbage = full_rows
+            .iter()
+            .filter(|row| {
+                self.placed_blocks
+                    .storage
+                    .iter()
+                    .any(|b| b.coordinates.row == **row && b.color == GARBAGE_COLOR)
+            })
+            .count();
+        (full_rows, garbage)
+    }
+    pub fn clear_rows(&mut self, rows: &[isize]) {
+        self.placed_blocks.clear_rows(rows);
+    }
+    pub fn insert_garbage_rows(&mut self, holes: &[isize]) -> bool {
+        let rows: Vec<HashSet<isize>> = holes
+            .iter()
+            .map(|hole| (0..self.size.cols).filter(|col| col != hole).collect())
+            .collect();
+        self.placed_blocks
+            .insert_rows_from_bottom(self.size, &rows, GARBAGE_COLOR)
+    }
+    pub fn check_intersections(&self, blocks: &HashSet<Block>) -> bool {
         let stationary_blocks = self.placed_blocks.get_blocks();
         for block in blocks {
-            if stationary_blocks.contains(&block)
+            if stationary_blocks.contains(block)
                 || !block.coordinates.is_inbound(self.size.rows, self.size.cols)
             {
                 return true;
@@ -171,7 +284,7 @@ impl Playfield {
         false
     }
 
-    pub fn check_collisions(&self, subject: &HashSet<Block>) -> CollisionResult {
+    fn check_collisions(&self, subject: &HashSet<Block>) -> CollisionResult {
         let stationary_blocks = self.placed_blocks.get_blocks();
 
         let mut collision_result = CollisionResult::new();
@@ -187,11 +300,6 @@ impl Playfield {
                         CollisionDirection::Left => collision_result.left = true,
                         CollisionDirection::Right => collision_result.right = true,
                     }
-                    dbg!(subject);
-                    dbg!(stationary_blocks);
-                    dbg!(block);
-                    dbg!(direction);
-                    dbg!(neighbour_coords);
                 }
             }
         }
@@ -199,13 +307,13 @@ impl Playfield {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
 pub struct PlayfieldSize {
     pub rows: isize,
     pub cols: isize,
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct PlacedBlocks {
     storage: HashSet<Block>,
 }
@@ -220,8 +328,68 @@ impl PlacedBlocks {
     fn put_blocks(&mut self, blocks: &HashSet<Block>) {
         self.storage.extend(blocks.iter());
     }
+    fn full_rows(&self, size: PlayfieldSize) -> Vec<isize> {
+        (0..size.rows)
+            .filter(|row| {
+                (0..size.cols).all(|col| self.storage.contains(&Position::new(*row, col).into()))
+            })
+            .collect()
+    }
+    fn clear_rows(&mut self, full_rows: &[isize]) {
+        if full_rows.is_empty() {
+            return;
+        }
+        self.storage = self
+            .storage
+            .iter()
+            .filter(|b| !full_rows.contains(&b.coordinates.row))
+            .map(|b| Block {
+                color: b.color,
+                coordinates: b.coordinates
+                    + Position::new(
+                        full_rows
+                            .iter()
+                            .filter(|row| **row > b.coordinates.row)
+                            .count() as isize,
+                        0,
+                    ),
+            })
+            .collect();
+    }
+
+    // pushes the stack up and fills the bottom with the given rows, each listing its filled
+    // columns, the last row ends up on the floor; returns true if blocks were pushed out the top
+    pub fn insert_rows_from_bottom(
+        &mut self,
+        size: PlayfieldSize,
+        rows: &[HashSet<isize>],
+        color: Color,
+    ) -> bool {
+        let shift = rows.len() as isize;
+        let overflow = self.storage.iter().any(|b| b.coordinates.row < shift);
+        self.storage = self
+            .storage
+            .iter()
+            .filter(|b| b.coordinates.row >= shift)
+            .map(|b| Block {
+                color: b.color,
+                coordinates: b.coordinates - Position::new(shift, 0),
+            })
+            .collect();
+        for (i, filled) in rows.iter().enumerate() {
+            let row = size.rows - shift + i as isize;
+            self.storage.extend(filled.iter().map(|col| Block {
+                color,
+                coordinates: Position::new(row, *col),
+            }));
+        }
+        overflow
+    }
 }
 
+// cloning keeps the timers on their wall clock deadlines: a clone put aside and played later
+// has to call restart_timers first, or gravity, lock delay and DAS all fire on its first step
+#[derive(Clone)]
 pub struct GameState {
     // merge into Playfield
     playfield: Playfield,
@@ -231,10 +399,61 @@ pub struct GameState {
     tetramino_manager: TetraminoManager,
     // merge into TimerManager
     pub descend_delay_timer: TimerMs,
-    pub place_delay_ms: usize,
 
-    // move to TetraminoManager
-    collision_state: CollisionState,
+    lines_cleared: usize,
+    garbage_lines_cleared: usize,
+    pieces_placed: usize,
+    game_over: bool,
+
+    auto_shift: Option<AutoShift>,
+    // rows of a held soft drop when it is not instant
+    soft_drop: Option<TimerMs>,
+    // only kept when the game was built with_finesse, judging a lock searches every placement
+    finesse: Option<FinesseTracker>,
+    scoring: Scoring,
+    ruleset: Ruleset,
+
+    garbage: GarbageQueue,
+    outgoing_garbage: usize,
+    attack_sent: usize,
+    seed: u64,
+    // only kept when the game was built with_events
+    events: Option<Vec<GameEvent>>,
+    // cells of the last piece locked, before any line it completed was cleared
+    last_lock: Option<(TetraminoKind, HashSet<Block>)>,
+    // full rows still on the board during the line clear delay, no piece is in play meanwhile
+    line_clear: Option<LineClear>,
+}
+
+#[derive(Clone)]
+struct LineClear {
+    rows: Vec<isize>,
+    timer: TimerMs,
+    // garbage pushed in while the rows were clearing, it goes in once they are gone
+    garbage_holes: Vec<isize>,
+}
+
+// enough about a lock to replay it on another copy of the game
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockRecord {
+    pub kind: TetraminoKind,
+    pub held: bool,
+    pub rotation_state: RotationState,
+    pub offset: Position,
+    // attack left for the opponent after cancelling
+    pub attack: usize,
+    // hole of every garbage row pushed in right after this lock
+    pub garbage_holes: Vec<isize>,
+}
+
+#[derive(Clone)]
+struct AutoShift {
+    key: KeyCode,
+    direction: Input,
+    charge: TimerMs,
+    charged: bool,
+    // steps of the auto repeat once charged, None when it is instant
+    repeat: Option<TimerMs>,
 }
 
 #[derive(EnumIter, Debug, PartialEq)]
@@ -269,37 +488,59 @@ impl CollisionResult {
         }
     }
 }
+#[derive(Clone)]
 enum CollisionState {
     Idle,
-    Delaying,
+    Delaying { timer: TimerMs },
+    Done,
 }
 
+#[derive(Clone)]
 struct PlacementDelayManager {
     collision_state: CollisionState,
-    delay_ms: usize,
-    timer: TimerMs,
+    delay: LockDelay,
+    // times the delay was started over by moving the current piece
+    resets: usize,
 }
 
 impl PlacementDelayManager {
-    fn new(delay_ms: usize) -> PlacementDelayManager {
+    fn new(delay: LockDelay) -> PlacementDelayManager {
         PlacementDelayManager {
             collision_state: CollisionState::Idle,
-            delay_ms,
-            timer: TimerMs::new(0),
+            delay,
+            resets: 0,
+        }
+    }
+    fn new_piece(&mut self) {
+        self.collision_state = CollisionState::Idle;
+        self.resets = 0;
+    }
+    // a move or rotation of the piece, which restarts a running delay under move reset
+    fn moved(&mut self) {
+        if let (LockReset::Move { limit }, CollisionState::Delaying { timer }) =
+            (self.delay.reset, &mut self.collision_state)
+            && self.resets < limit
+        {
+            *timer = timer.reset();
+            self.resets += 1;
         }
     }
     fn delay_passed(&mut self, is_colliding: bool) -> bool {
         match self.collision_state {
-            CollisionState::Idle => {
+            CollisionState::Idle | CollisionState::Done => {
                 if is_colliding {
-                    self.collision_state = CollisionState::Delaying;
-                    self.timer = TimerMs::new(self.delay_ms);
+                    self.collision_state = CollisionState::Delaying {
+                        timer: TimerMs::new(self.delay.delay_ms),
+                    };
                 }
                 false
             }
-            CollisionState::Delaying => {
-                if self.timer.update() {
+            CollisionState::Delaying { ref mut timer } => {
+                if !is_colliding {
                     self.collision_state = CollisionState::Idle;
+                    false
+                } else if timer.update() {
+                    self.collision_state = CollisionState::Done;
                     true
                 } else {
                     false
@@ -309,27 +550,76 @@ impl PlacementDelayManager {
     }
 }
 
+// the seeded source of pieces after any fixed queue
+#[derive(Clone)]
+struct PieceGenerator {
+    randomizer: Randomizer,
+    rng: StdRng,
+    // what is left of the current bag, drawn from the back
+    bag: Vec<TetraminoKind>,
+}
+
+impl PieceGenerator {
+    fn new(randomizer: Randomizer, seed: u64) -> PieceGenerator {
+        PieceGenerator {
+            randomizer,
+            rng: StdRng::seed_from_u64(seed),
+            bag: Vec::new(),
+        }
+    }
+    fn draw(&mut self) -> TetraminoKind {
+        match self.randomizer {
+            Randomizer::Uniform => self.rng.random(),
+            Randomizer::Bag => {
+                if self.bag.is_empty() {
+                    self.bag = TetraminoKind::iter().collect();
+                    self.bag.shuffle(&mut self.rng);
+                }
+                self.bag.pop().expect("a refilled bag is never empty")
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
 struct TetraminoManager {
     active: ActiveTetramino,
     gravity_delay: TimerMs,
     placement_delay: PlacementDelayManager,
     next: TetraminoKind,
     hold: Option<Tetramino>,
+    hold_used: bool,
+    // kick test that placed the last successful rotation, cleared by any other movement
+    last_kick: Option<usize>,
+    // pieces dealt before the random sequence takes over
+    queue: VecDeque<TetraminoKind>,
+    generator: PieceGenerator,
 }
 
 impl TetraminoManager {
-    pub fn new(gravity_delay_ms: usize, placement_delay_ms: usize) -> TetraminoManager {
+    pub fn new(
+        gravity_delay_ms: usize,
+        lock_delay: LockDelay,
+        randomizer: Randomizer,
+        seed: u64,
+    ) -> TetraminoManager {
+        let mut generator = PieceGenerator::new(randomizer, seed);
         TetraminoManager {
-            active: ActiveTetramino::new(Tetramino::construct(rand::random())),
+            active: ActiveTetramino::new(Tetramino::construct(generator.draw())),
             gravity_delay: TimerMs::new(gravity_delay_ms),
-            placement_delay: PlacementDelayManager::new(placement_delay_ms),
-            next: rand::random(),
+            placement_delay: PlacementDelayManager::new(lock_delay),
+            next: generator.draw(),
             hold: None,
+            hold_used: false,
+            last_kick: None,
+            queue: VecDeque::new(),
+            generator,
         }
     }
     pub fn propogate_gravity(&mut self) {
         self.active
             .translate_with_offset(Position { row: 1, col: 0 });
+        self.last_kick = None;
     }
     pub fn with_offset(self, offset: Position) -> TetraminoManager {
         TetraminoManager {
@@ -338,11 +628,68 @@ impl TetraminoManager {
             placement_delay: self.placement_delay,
             next: self.next,
             hold: self.hold,
+            hold_used: self.hold_used,
+            last_kick: self.last_kick,
+            queue: self.queue,
+            generator: self.generator,
         }
     }
+    // restarts the piece sequence from a seed, so games sharing it get the same pieces
+    pub fn reseed(&mut self, seed: u64) {
+        self.generator = PieceGenerator::new(self.generator.randomizer, seed);
+        self.queue.clear();
+        self.active = ActiveTetramino::new(Tetramino::construct(self.generator.draw()));
+        self.next = self.generator.draw();
+        self.hold = None;
+        self.hold_used = false;
+        self.last_kick = None;
+        self.placement_delay.new_piece();
+    }
+    // deals a fixed sequence first, the seeded random pieces follow it
+    pub fn set_queue(&mut self, pieces: &[TetraminoKind]) {
+        self.queue = pieces.iter().copied().collect();
+        self.hold = None;
+        self.hold_used = false;
+        self.next = self.deal();
+        self.next_tetramino();
+    }
+    pub fn set_hold(&mut self, kind: Option<TetraminoKind>) {
+        self.hold = kind.map(Tetramino::construct);
+        self.hold_used = false;
+    }
+    // the pieces that will follow the active one, read ahead on a copy of the generator
+    pub fn preview(&self, count: usize) -> Vec<TetraminoKind> {
+        let mut queue = self.queue.clone();
+        let mut generator = self.generator.clone();
+        std::iter::once(self.next)
+            .chain(std::iter::repeat_with(|| {
+                queue.pop_front().unwrap_or_else(|| generator.draw())
+            }))
+            .take(count)
+            .collect()
+    }
+    fn deal(&mut self) -> TetraminoKind {
+        self.queue
+            .pop_front()
+            .unwrap_or_else(|| self.generator.draw())
+    }
     pub fn next_tetramino(&mut self) {
         self.active = ActiveTetramino::new(Tetramino::construct(self.next));
-        self.next = rand::random();
+        self.next = self.deal();
+        self.hold_used = false;
+        self.last_kick = None;
+        self.placement_delay.new_piece();
+    }
+    pub fn swap_hold(&mut self) {
+        let held = Tetramino::construct(self.active.kind());
+        match self.hold.take() {
+            Some(tetramino) => self.active = ActiveTetramino::new(tetramino),
+            None => self.next_tetramino(),
+        }
+        self.hold = Some(held);
+        self.hold_used = true;
+        self.last_kick = None;
+        self.placement_delay.new_piece();
     }
     pub fn rotate(&self, direction: RotationDirection) -> RotationResult {
         self.active.get_rotation_result(direction)
@@ -350,28 +697,616 @@ impl TetraminoManager {
 }
 
 impl GameState {
+    // default rules on a board of `playfield_size` without hidden rows
     pub fn new(
         playfield_size: PlayfieldSize,
         gravity_delay_ms: usize,
         placement_delay_ms: usize,
     ) -> GameState {
+        GameState::from_ruleset(Ruleset {
+            gravity: GravityCurve::Guideline {
+                level_one_delay_ms: gravity_delay_ms,
+            },
+            lock_delay: LockDelay {
+                delay_ms: placement_delay_ms,
+                reset: LockReset::Step,
+            },
+            ..Ruleset::with_size(playfield_size)
+        })
+    }
+
+    pub fn from_ruleset(ruleset: Ruleset) -> GameState {
+        let seed = rand::random();
+        let gravity_delay_ms = ruleset.gravity.delay_ms(1);
+        let playfield = Playfield::new(ruleset.size()).with_hidden_rows(ruleset.hidden_rows);
+        let tetramino_manager = TetraminoManager::new(
+            gravity_delay_ms,
+            ruleset.lock_delay,
+            ruleset.randomizer,
+            seed,
+        )
+        .with_offset(playfield.spawn_offset());
         GameState {
-            playfield: Playfield::new(playfield_size),
-            descend_delay_timer: TimerMs::new(200),
-            place_delay_ms: 1000,
-            collision_state: CollisionState::Idle,
-            tetramino_manager: TetraminoManager::new(gravity_delay_ms, placement_delay_ms)
-                .with_offset(Position::new(
-                    playfield_size.rows / 2,
-                    playfield_size.cols / 2,
-                )),
+            playfield,
+            descend_delay_timer: TimerMs::new(gravity_delay_ms),
+            lines_cleared: 0,
+            garbage_lines_cleared: 0,
+            pieces_placed: 0,
+            game_over: false,
+            auto_shift: None,
+            soft_drop: None,
+            finesse: None,
+            scoring: Scoring::new(1, DEFAULT_LINES_PER_LEVEL),
+            garbage: GarbageQueue::new(ruleset.garbage_delay_ms, seed),
+            ruleset,
+            outgoing_garbage: 0,
+            attack_sent: 0,
+            seed,
+            events: None,
+            last_lock: None,
+            line_clear: None,
+            tetramino_manager,
+        }
+    }
+
+    pub fn with_level(self, level: usize) -> GameState {
+        self.with_level_progression(level, DEFAULT_LINES_PER_LEVEL)
+    }
+    pub fn with_level_progression(
+        mut self,
+        start_level: usize,
+        lines_per_level: usize,
+    ) -> GameState {
+        self.scoring = Scoring::new(start_level, lines_per_level);
+        self.update_gravity();
+        self
+    }
+
+    pub fn with_attack_table(mut self, attack_table: AttackTable) -> GameState {
+        self.ruleset.attack_table = attack_table;
+        self
+    }
+    pub fn with_garbage_delay(mut self, delay_ms: usize) -> GameState {
+        self.ruleset.garbage_delay_ms = delay_ms;
+        self.garbage.set_delay(delay_ms);
+        self
+    }
+    pub fn with_events(mut self) -> GameState {
+        self.events = Some(Vec::new());
+        self
+    }
+    pub fn with_finesse(mut self) -> GameState {
+        self.finesse = Some(FinesseTracker::default());
+        self
+    }
+    pub fn with_queue(mut self, pieces: &[TetraminoKind]) -> GameState {
+        self.tetramino_manager.set_queue(pieces);
+        self.spawn_active();
+        self
+    }
+    pub fn with_hold(mut self, kind: Option<TetraminoKind>) -> GameState {
+        self.tetramino_manager.set_hold(kind);
+        self
+    }
+    pub fn with_seed(mut self, seed: u64) -> GameState {
+        self.seed = seed;
+        self.tetramino_manager.reseed(seed);
+        self.garbage = GarbageQueue::new(self.garbage.delay_ms(), seed);
+        self.spawn_active();
+        self
+    }
+
+    pub fn ruleset(&self) -> &Ruleset {
+        &self.ruleset
+    }
+    // the rotations of a piece as this game's rotation system moves it
+    pub fn shape_table(&self, kind: TetraminoKind) -> ShapeTable {
+        ShapeTable::new(kind).with_rotation_system(self.ruleset.rotation)
+    }
+    pub fn playfield(&self) -> &Playfield {
+        &self.playfield
+    }
+    pub fn playfield_size(&self) -> PlayfieldSize {
+        self.playfield.size()
+    }
+    pub fn placed_blocks(&self) -> &PlacedBlocks {
+        self.playfield.placed_blocks()
+    }
+    pub fn current_tetramino(&self) -> &ActiveTetramino {
+        &self.tetramino_manager.active
+    }
+    pub fn next_kind(&self) -> TetraminoKind {
+        self.tetramino_manager.next
+    }
+    pub fn preview(&self, count: usize) -> Vec<TetraminoKind> {
+        self.tetramino_manager.preview(count)
+    }
+    // as many upcoming pieces as the ruleset shows
+    pub fn next_queue(&self) -> Vec<TetraminoKind> {
+        self.preview(self.ruleset.preview)
+    }
+    pub fn hold_kind(&self) -> Option<TetraminoKind> {
+        self.tetramino_manager.hold.as_ref().map(|t| t.kind())
+    }
+    pub fn can_hold(&self) -> bool {
+        self.ruleset.hold && !self.tetramino_manager.hold_used
+    }
+    pub fn lines_cleared(&self) -> usize {
+        self.lines_cleared
+    }
+    pub fn garbage_lines_cleared(&self) -> usize {
+        self.garbage_lines_cleared
+    }
+    pub fn pieces_placed(&self) -> usize {
+        self.pieces_placed
+    }
+    pub fn is_game_over(&self) -> bool {
+        self.game_over
+    }
+    // the full rows of a line clear that is still running, empty while a piece is in play
+    pub fn clearing_rows(&self) -> &[isize] {
+        self.line_clear
+            .as_ref()
+            .map_or(&[], |line_clear| line_clear.rows.as_slice())
+    }
+    pub fn finesse(&self) -> Option<&FinesseTracker> {
+        self.finesse.as_ref()
+    }
+    pub fn scoring(&self) -> &Scoring {
+        &self.scoring
+    }
+    pub fn score(&self) -> usize {
+        self.scoring.score()
+    }
+    pub fn level(&self) -> usize {
+        self.scoring.level()
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    // events since the last call, only kept when the game was built with_events
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        self.events.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+    // Zobrist hash of the placed cells, where the piece in play is and how it is turned, every
+    // slot of the preview, the held piece and whether hold is still available; equal on every
+    // machine for equal positions, whatever order the cells were placed in
+    pub fn board_hash(&self) -> u64 {
+        let position = |row: isize, col: isize| (row as u16 as u64) << 16 | col as u16 as u64;
+        let cells = self
+            .placed_blocks()
+            .get_blocks()
+            .iter()
+            .map(|b| zobrist_key(position(b.coordinates.row, b.coordinates.col)));
+        // slot 0 is the piece in play, the preview follows in order
+        let queue = std::iter::once(self.current_tetramino().kind())
+            .chain(self.next_queue())
+            .enumerate()
+            .map(|(slot, kind)| zobrist_key(1 << 40 | (slot as u64) << 8 | kind as u64));
+        let hold = self
+            .hold_kind()
+            .map(|kind| zobrist_key(1 << 42 | kind as u64));
+        let hold_used = (!self.can_hold()).then(|| zobrist_key(1 << 41));
+        let active = self.current_tetramino();
+        let offset = active.offset();
+        let placement = zobrist_key(
+            1 << 43 | (active.rotation_state() as u64) << 32 | position(offset.row, offset.col),
+        );
+        cells
+            .chain(queue)
+            .chain(hold)
+            .chain(hold_used)
+            .chain(std::iter::once(placement))
+            .fold(0, |hash, key| hash ^ key)
+    }
+    // timers keep running on the wall clock, so a state cloned a while ago would see its
+    // gravity, lock delay and DAS expire at once; this starts them over from now, and has to be
+    // called on every clone, undo snapshot or paused game before it is played again
+    pub fn restart_timers(&mut self) {
+        self.descend_delay_timer = self.descend_delay_timer.reset();
+        let manager = &mut self.tetramino_manager;
+        manager.gravity_delay = manager.gravity_delay.reset();
+        if let CollisionState::Delaying { timer } = &mut manager.placement_delay.collision_state {
+            *timer = timer.reset();
+        }
+        if let Some(auto_shift) = &mut self.auto_shift {
+            auto_shift.charge = auto_shift.charge.reset();
+        }
+        self.soft_drop = None;
+        if let Some(line_clear) = &mut self.line_clear {
+            line_clear.timer = line_clear.timer.reset();
+        }
+        self.garbage.restart_timers();
+    }
+    pub fn last_lock(&self) -> Option<(TetraminoKind, &HashSet<Block>)> {
+        self.last_lock
+            .as_ref()
+            .map(|(kind, blocks)| (*kind, blocks))
+    }
+    // fills cells directly, as a prepared board; the piece in play tops out if it is covered
+    pub fn place_blocks(&mut self, blocks: &HashSet<Block>) {
+        self.playfield.put_blocks(blocks);
+        if self
+            .playfield
+            .check_intersections(&self.tetramino_manager.active.get_blocks_with_offset())
+        {
+            self.top_out();
+        }
+    }
+    pub fn attack_table(&self) -> &AttackTable {
+        &self.ruleset.attack_table
+    }
+    // every line of attack produced, including what went into cancelling incoming garbage
+    pub fn attack_sent(&self) -> usize {
+        self.attack_sent
+    }
+    pub fn incoming_garbage(&self) -> usize {
+        self.garbage.total()
+    }
+    pub fn ready_garbage(&mut self) -> usize {
+        self.garbage.ready()
+    }
+
+    fn emit(&mut self, event: GameEvent) {
+        if let Some(events) = self.events.as_mut() {
+            events.push(event);
+        }
+    }
+    fn top_out(&mut self) {
+        if !self.game_over {
+            self.game_over = true;
+            self.emit(GameEvent::GameOver);
+        }
+    }
+
+    fn check_collision(&self) -> CollisionResult {
+        self.playfield
+            .check_collisions(&self.tetramino_manager.active.get_blocks_with_offset())
+    }
+    fn translate_cur_tetramino(&mut self, offset: Position) {
+        self.tetramino_manager.active.translate_with_offset(offset);
+        self.tetramino_manager.last_kick = None;
+        self.tetramino_manager.placement_delay.moved();
+    }
+    // scores the piece in play as it lies; the rows it fills go at once without a delay, or
+    // stay for clear_delay_ms with the next piece held back until finish_line_clear
+    fn place_current_tetramino(&mut self, clear_delay_ms: usize) {
+        let active = &self.tetramino_manager.active;
+        if let Some(finesse) = &mut self.finesse {
+            finesse.lock(
+                &self.playfield,
+                self.ruleset.rotation,
+                active.kind(),
+                &active.get_blocks_with_offset(),
+            );
+        }
+        let kind = active.kind();
+        let spin = self.detect_spin();
+        let blocks = self.tetramino_manager.active.get_blocks_with_offset();
+        self.playfield.put_blocks(&blocks);
+        self.emit(GameEvent::Locked {
+            kind,
+            cells: blocks.iter().map(|block| block.coordinates).collect(),
+            spin,
+        });
+        self.last_lock = Some((kind, blocks));
+        let (rows, garbage_lines) = self.playfield.full_rows();
+        let in_rows = |block: &&Block| rows.contains(&block.coordinates.row);
+        // what the full rows hold, only looked up when there are events to tell
+        let mut cleared_cells: Vec<Block> = match self.events {
+            Some(_) => self
+                .placed_blocks()
+                .get_blocks()
+                .iter()
+                .filter(in_rows)
+                .copied()
+                .collect(),
+            None => Vec::new(),
+        };
+        cleared_cells.sort_by_key(|block| (block.coordinates.row, block.coordinates.col));
+        let lines = rows.len();
+        self.lines_cleared += lines;
+        self.garbage_lines_cleared += garbage_lines;
+        self.pieces_placed += 1;
+
+        let perfect_clear = lines > 0
+            && self
+                .placed_blocks()
+                .get_blocks()
+                .iter()
+                .all(|block| in_rows(&block));
+        let clear_delay_ms = if lines > 0 { clear_delay_ms } else { 0 };
+        let level = self.scoring.level();
+        let clear = self
+            .scoring
+            .lock(kind, lines, spin, perfect_clear, self.lines_cleared);
+        let attack = self.ruleset.attack_table.attack(&clear);
+        if lines > 0 {
+            self.emit(GameEvent::LinesCleared {
+                rows: rows.clone(),
+                cells: cleared_cells,
+                delay_ms: clear_delay_ms,
+                spin,
+                garbage: garbage_lines,
+                back_to_back: clear.back_to_back,
+                combo: clear.combo,
+                perfect_clear,
+                attack,
+            });
+        }
+        if self.scoring.level() != level {
+            self.update_gravity();
+            self.emit(GameEvent::LevelUp {
+                level: self.scoring.level(),
+            });
+        }
+
+        self.attack_sent += attack;
+        let outgoing = self.garbage.cancel(attack);
+        self.outgoing_garbage += outgoing;
+        let holes = if lines == 0 {
+            self.garbage.take_ready()
+        } else {
+            Vec::new()
+        };
+        self.insert_garbage_rows(&holes);
+
+        let active = &self.tetramino_manager.active;
+        let record = LockRecord {
+            kind,
+            held: self.tetramino_manager.hold_used,
+            rotation_state: active.rotation_state(),
+            offset: active.offset,
+            attack: outgoing,
+            garbage_holes: holes,
+        };
+        self.emit(GameEvent::LockRecorded { record });
+        if clear_delay_ms > 0 {
+            self.line_clear = Some(LineClear {
+                rows,
+                timer: TimerMs::new(clear_delay_ms),
+                garbage_holes: Vec::new(),
+            });
+        } else {
+            self.playfield.clear_rows(&rows);
+        }
+    }
+    // locks the piece in play and brings in the next one, unless its rows are still clearing
+    fn lock(&mut self, clear_delay_ms: usize) {
+        self.place_current_tetramino(clear_delay_ms);
+        if self.line_clear.is_none() {
+            self.next_turn();
+        }
+    }
+    // the line clear delay is over: the rows go, garbage that came meanwhile goes in and the
+    // next piece spawns
+    fn finish_line_clear(&mut self) {
+        let Some(line_clear) = self.line_clear.take() else {
+            return;
+        };
+        self.playfield.clear_rows(&line_clear.rows);
+        self.insert_garbage_rows(&line_clear.garbage_holes);
+        self.descend_delay_timer = self.descend_delay_timer.reset();
+        self.next_turn();
+    }
+    // replays a lock recorded on another copy of this game
+    pub fn replay_lock(&mut self, record: &LockRecord) {
+        if record.held {
+            self.hold();
+        }
+        let active = &mut self.tetramino_manager.active;
+        while active.rotation_state() != record.rotation_state {
+            active.shape = active
+                .shape
+                .get_rotated_and_offsets(RotationDirection::Clockwise)
+                .tetramino;
+        }
+        active.offset = record.offset;
+        self.place_current_tetramino(0);
+        self.insert_garbage_rows(&record.garbage_holes);
+        self.next_turn();
+    }
+    fn insert_garbage_rows(&mut self, holes: &[isize]) {
+        if !holes.is_empty() && self.playfield.insert_garbage_rows(holes) {
+            self.top_out();
+        }
+    }
+    fn update_gravity(&mut self) {
+        self.descend_delay_timer =
+            TimerMs::new(self.ruleset.gravity.delay_ms(self.scoring.level()));
+    }
+
+    // three corner rule: a T rotated into place with three of the corners around its center
+    // occupied; it is a mini unless both corners its nub points at are filled or it needed the
+    // last kick test
+    fn detect_spin(&self) -> SpinKind {
+        let active = &self.tetramino_manager.active;
+        let Some(kick) = self.tetramino_manager.last_kick else {
+            return SpinKind::None;
+        };
+        if active.kind() != TetraminoKind::T {
+            return SpinKind::None;
+        }
+        let center = active.shape.rotation_center() + active.offset;
+        let occupied = |row: isize, col: isize| {
+            let corner = center + Position::new(row, col);
+            !corner.is_inbound(self.playfield.size.rows, self.playfield.size.cols)
+                || self.placed_blocks().get_blocks().contains(&corner.into())
+        };
+        let corners = [(-1, -1), (-1, 1), (1, -1), (1, 1)]
+            .iter()
+            .filter(|(row, col)| occupied(*row, *col))
+            .count();
+        if corners < 3 {
+            return SpinKind::None;
+        }
+        let front = match active.rotation_state() {
+            RotationState::Init => [(-1, -1), (-1, 1)],
+            RotationState::Right => [(-1, 1), (1, 1)],
+            RotationState::Flip => [(1, -1), (1, 1)],
+            RotationState::Left => [(-1, -1), (1, -1)],
+        };
+        if front.iter().all(|(row, col)| occupied(*row, *col)) || kick == 4 {
+            SpinKind::Full
+        } else {
+            SpinKind::Mini
+        }
+    }
+    fn next_turn(&mut self) {
+        self.tetramino_manager.next_tetramino();
+        self.spawn_active();
+    }
+    fn spawn_active(&mut self) {
+        if let Some(finesse) = &mut self.finesse {
+            finesse.reset_piece();
+        }
+        self.tetramino_manager.active.offset = self.playfield.spawn_offset();
+        if self
+            .playfield
+            .check_intersections(&self.tetramino_manager.active.get_blocks_with_offset())
+        {
+            self.top_out();
+        } else if !self.game_over {
+            self.emit(GameEvent::PieceSpawned {
+                kind: self.tetramino_manager.active.kind(),
+            });
+        }
+    }
+
+    pub fn try_translate(&mut self, offset: Position) -> bool {
+        let moved = self.shift(offset);
+        if moved {
+            self.emit(GameEvent::Moved { offset });
+        }
+        moved
+    }
+    // try_translate without the event, for drops
+    fn shift(&mut self, offset: Position) -> bool {
+        let active = &self.tetramino_manager.active;
+        if self
+            .playfield
+            .check_intersections(&active.shape.get_blocks_with_offset(active.offset + offset))
+        {
+            return false;
+        }
+        self.translate_cur_tetramino(offset);
+        true
+    }
+    // one row of a soft drop that isn't instant
+    fn soft_drop_step(&mut self) {
+        if self.shift(Position::new(1, 0)) {
+            self.scoring.add_drop(1, false);
+            self.emit(GameEvent::SoftDropped { rows: 1 });
+        }
+    }
+    pub fn apply_input(&mut self, input: Input) {
+        if let Some(finesse) = &mut self.finesse {
+            finesse.record(input.into());
+        }
+        match input {
+            Input::Left => {
+                self.try_translate(Position::new(0, -1));
+            }
+            Input::Right => {
+                self.try_translate(Position::new(0, 1));
+            }
+            Input::RotateClockwise => self.try_rotate(RotationDirection::Clockwise),
+            Input::RotateCounterClockwise => self.try_rotate(RotationDirection::CounterClockwise),
+            Input::SoftDrop => {
+                let mut rows = 0;
+                while self.shift(Position::new(1, 0)) {
+                    rows += 1;
+                }
+                self.scoring.add_drop(rows, false);
+                if rows > 0 {
+                    self.emit(GameEvent::SoftDropped { rows });
+                }
+            }
+        }
+    }
+    // DAS with an instant auto repeat: once charged the piece travels all the way to the wall
+    pub fn auto_shift(&mut self, direction: Input) {
+        let offset = match direction {
+            Input::Left => Position::new(0, -1),
+            Input::Right => Position::new(0, 1),
+            _ => return,
+        };
+        if self.try_translate(offset) {
+            while self.try_translate(offset) {}
+        }
+    }
+    // lines clear at once, for bots and tools that play a piece at a time; players get the
+    // ruleset's line clear delay through process_logic
+    pub fn hard_drop(&mut self) {
+        self.drop_and_lock(0);
+    }
+    fn drop_and_lock(&mut self, clear_delay_ms: usize) {
+        let mut rows = 0;
+        while self.shift(Position::new(1, 0)) {
+            rows += 1;
+        }
+        self.scoring.add_drop(rows, true);
+        let cells = self.tetramino_manager.active.get_blocks_with_offset();
+        self.emit(GameEvent::HardDropped {
+            rows,
+            cells: cells.iter().map(|block| block.coordinates).collect(),
+        });
+        self.lock(clear_delay_ms);
+    }
+    // garbage rows with one hole each, pushed in from the bottom; the active piece is lifted
+    // out of the way and overflowing the top of the playfield ends the game
+    pub fn add_garbage_rows(&mut self, holes: &[isize]) {
+        if let Some(line_clear) = &mut self.line_clear {
+            line_clear.garbage_holes.extend_from_slice(holes);
+            return;
+        }
+        self.insert_garbage_rows(holes);
+        while self
+            .playfield
+            .check_intersections(&self.tetramino_manager.active.get_blocks_with_offset())
+        {
+            if self.tetramino_manager.active.offset.row <= 0 {
+                self.top_out();
+                break;
+            }
+            self.tetramino_manager
+                .active
+                .translate_with_offset(Position::new(-1, 0));
+        }
+    }
+    // holes drawn from here follow the game's seed, so replays and net games get the same ones
+    pub fn garbage_rng(&mut self) -> &mut StdRng {
+        self.garbage.rng()
+    }
+    // garbage from an opponent, it waits in the queue until a lock without a line clear
+    pub fn receive_garbage(&mut self, lines: usize) {
+        self.garbage.receive(lines, self.playfield.size.cols);
+        self.emit(GameEvent::GarbageReceived { lines });
+    }
+    // attack left after cancelling, to be sent to the opponent
+    pub fn take_outgoing_garbage(&mut self) -> usize {
+        std::mem::take(&mut self.outgoing_garbage)
+    }
+    pub fn hold(&mut self) {
+        if self.can_hold() {
+            self.tetramino_manager.swap_hold();
+            if let Some(kind) = self.hold_kind() {
+                self.emit(GameEvent::Hold { kind });
+            }
+            self.spawn_active();
         }
     }
 
     pub fn try_rotate(&mut self, direction: RotationDirection) {
         let rotation_result = self.tetramino_manager.rotate(direction);
 
-        for kick_offset in rotation_result.kick_offsets {
+        let kick_tests = self.ruleset.rotation.kick_tests();
+        for (kick, kick_offset) in rotation_result
+            .kick_offsets
+            .into_iter()
+            .take(kick_tests)
+            .enumerate()
+        {
             if !self.playfield.check_intersections(
                 &rotation_result
                     .tetramino
@@ -379,6 +1314,12 @@ impl GameState {
             ) {
                 self.tetramino_manager.active.shape = rotation_result.tetramino;
                 self.tetramino_manager.active.offset += kick_offset;
+                self.tetramino_manager.last_kick = Some(kick);
+                self.tetramino_manager.placement_delay.moved();
+                self.emit(GameEvent::Rotated {
+                    direction,
+                    kick_index: kick,
+                });
                 break;
             }
         }
@@ -386,6 +1327,15 @@ impl GameState {
     pub fn update(&mut self) {}
 }
 
+// fixed pseudo random 64-bit key for a feature of a position, splitmix64 of the feature
+fn zobrist_key(feature: u64) -> u64 {
+    let mut key = feature.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    key = (key ^ (key >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    key = (key ^ (key >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    key ^ (key >> 31)
+}
+
+// a deadline on the wall clock, not on game time; see GameState::restart_timers
 #[derive(Clone, Copy)]
 pub struct TimerMs {
     deadline: Instant,
@@ -415,48 +1365,179 @@ impl TimerMs {
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyMap {
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub rotate_clockwise: KeyCode,
+    pub rotate_counter_clockwise: KeyCode,
+    pub soft_drop: KeyCode,
+    pub hard_drop: KeyCode,
+    pub hold: KeyCode,
+    pub skip: KeyCode,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        KeyMap {
+            left: KeyCode::A,
+            right: KeyCode::D,
+            rotate_clockwise: KeyCode::E,
+            rotate_counter_clockwise: KeyCode::Q,
+            soft_drop: KeyCode::S,
+            hard_drop: KeyCode::Space,
+            hold: KeyCode::C,
+            skip: KeyCode::N,
+        }
+    }
+}
+
+impl KeyMap {
+    // arrows and the keys around them, so a second player can share the keyboard
+    pub fn arrows() -> KeyMap {
+        KeyMap {
+            left: KeyCode::Left,
+            right: KeyCode::Right,
+            rotate_clockwise: KeyCode::Up,
+            rotate_counter_clockwise: KeyCode::RightControl,
+            soft_drop: KeyCode::Down,
+            hard_drop: KeyCode::Enter,
+            hold: KeyCode::RightShift,
+            skip: KeyCode::Backspace,
+        }
+    }
+
+    pub fn key_for(&self, input: Input) -> KeyCode {
+        match input {
+            Input::Left => self.left,
+            Input::Right => self.right,
+            Input::RotateClockwise => self.rotate_clockwise,
+            Input::RotateCounterClockwise => self.rotate_counter_clockwise,
+            Input::SoftDrop => self.soft_drop,
+        }
+    }
+}
+
 pub fn process_logic(game_state: &mut GameState, input: InputEvent) {
+    process_logic_with_keys(game_state, input, &KeyMap::default());
+}
+
+pub fn process_logic_with_keys(game_state: &mut GameState, input: InputEvent, keys: &KeyMap) {
+    process_logic_with_handling(game_state, input, keys, &Handling::default());
+}
+
+pub fn process_logic_with_handling(
+    game_state: &mut GameState,
+    input: InputEvent,
+    keys: &KeyMap,
+    handling: &Handling,
+) {
+    if game_state.is_game_over() {
+        return;
+    }
+    // nothing is in play until the rows are gone, presses made meanwhile are dropped
+    if let Some(line_clear) = &mut game_state.line_clear {
+        if !line_clear.timer.update() {
+            return;
+        }
+        game_state.finish_line_clear();
+        if game_state.is_game_over() {
+            return;
+        }
+    }
     let collision = game_state.check_collision();
-    if input.keys.contains(&KeyCode::A) && !collision.left {
-        game_state.translate_cur_tetramino(Position { row: 0, col: -1 });
+    for (key, direction) in [(keys.left, Input::Left), (keys.right, Input::Right)] {
+        if input.keys.contains(&key) {
+            game_state.apply_input(direction);
+            game_state.auto_shift = Some(AutoShift {
+                key,
+                direction,
+                charge: TimerMs::new(handling.das_ms),
+                charged: false,
+                repeat: None,
+            });
+        }
+    }
+    if let Some(mut auto_shift) = game_state.auto_shift.take()
+        && input.held.contains(&auto_shift.key)
+    {
+        let against_wall = match auto_shift.direction {
+            Input::Left => collision.left,
+            _ => collision.right,
+        };
+        let mut just_charged = false;
+        if !auto_shift.charged && auto_shift.charge.update() {
+            auto_shift.charged = true;
+            just_charged = true;
+            auto_shift.repeat = (handling.arr_ms > 0).then(|| TimerMs::new(handling.arr_ms));
+            if let Some(finesse) = &mut game_state.finesse {
+                finesse.record_auto_shift(match auto_shift.direction {
+                    Input::Left => FinesseInput::DasLeft,
+                    _ => FinesseInput::DasRight,
+                });
+            }
+        }
+        if auto_shift.charged && !against_wall {
+            match auto_shift.repeat.as_mut() {
+                None => game_state.auto_shift(auto_shift.direction),
+                Some(repeat) => {
+                    if just_charged || repeat.update() {
+                        game_state.try_translate(match auto_shift.direction {
+                            Input::Left => Position::new(0, -1),
+                            _ => Position::new(0, 1),
+                        });
+                    }
+                }
+            }
+        }
+        game_state.auto_shift = Some(auto_shift);
     }
-    if input.keys.contains(&KeyCode::D) && !collision.right {
-        game_state.translate_cur_tetramino(Position { row: 0, col: 1 });
+    for input_kind in [Input::RotateClockwise, Input::RotateCounterClockwise] {
+        if input.keys.contains(&keys.key_for(input_kind)) {
+            game_state.apply_input(input_kind);
+        }
     }
-    if input.keys.contains(&KeyCode::E) {
-        game_state.try_rotate(RotationDirection::Clockwise);
+    if handling.soft_drop_factor == 0 {
+        if input.keys.contains(&keys.soft_drop) {
+            game_state.apply_input(Input::SoftDrop);
+        }
+    } else if input.held.contains(&keys.soft_drop) {
+        // a held soft drop falls soft_drop_factor times faster than gravity
+        let pressed = input.keys.contains(&keys.soft_drop);
+        if pressed && let Some(finesse) = &mut game_state.finesse {
+            finesse.record(Input::SoftDrop.into());
+        }
+        let delay_ms = (game_state.descend_delay_timer.wait_ms / handling.soft_drop_factor).max(1);
+        let timer = game_state
+            .soft_drop
+            .get_or_insert_with(|| TimerMs::new(delay_ms));
+        if pressed || timer.update() {
+            game_state.soft_drop_step();
+        }
+    } else {
+        game_state.soft_drop = None;
     }
-    if input.keys.contains(&KeyCode::Q) {
-        game_state.try_rotate(RotationDirection::CounterClockwise);
+    if input.keys.contains(&keys.hold) {
+        game_state.hold();
     }
-    if input.keys.contains(&KeyCode::N) {
+    if input.keys.contains(&keys.skip) {
         game_state.next_turn();
     }
+    if input.keys.contains(&keys.hard_drop) {
+        game_state.drop_and_lock(game_state.ruleset.line_clear_delay_ms);
+        return;
+    }
 
+    let collision = game_state.check_collision();
     if !collision.down && game_state.descend_delay_timer.update() {
-        game_state.translate_cur_tetramino(Position::new(1, 0));
-        game_state.collision_state = CollisionState::Idle;
+        game_state.tetramino_manager.propogate_gravity();
     }
 
-    game_state.collision_state = match game_state.collision_state {
-        CollisionState::Idle => {
-            if collision.down {
-                CollisionState::Delaying {
-                    timer: TimerMs::new(game_state.place_delay_ms),
-                }
-            } else {
-                CollisionState::Idle
-            }
-        }
-        CollisionState::Delaying { mut timer } => {
-            if timer.update() {
-                game_state.place_current_tetramino();
-                game_state.next_turn();
-                CollisionState::Done
-            } else {
-                CollisionState::Delaying { timer }
-            }
-        }
-        CollisionState::Done => CollisionState::Idle,
-    };
+    if game_state
+        .tetramino_manager
+        .placement_delay
+        .delay_passed(collision.down)
+    {
+        game_state.lock(game_state.ruleset.line_clear_delay_ms);
+    }
 }
@@ -0,0 +1,164 @@
+use std::{collections::VecDeque, time::Duration};
+
+use crate::{InputEvent, KeyMap, events::GameEvent};
+
+// how far back the rolling values look
+pub const ROLLING_WINDOW: Duration = Duration::from_secs(10);
+
+// what happened in a game between two observations
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Sample {
+    pieces: usize,
+    lines: usize,
+    attack: usize,
+    garbage_cleared: usize,
+    keys: usize,
+}
+
+impl Sample {
+    fn add(&mut self, other: &Sample) {
+        self.pieces += other.pieces;
+        self.lines += other.lines;
+        self.attack += other.attack;
+        self.garbage_cleared += other.garbage_cleared;
+        self.keys += other.keys;
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StatLine {
+    pub pieces_per_second: f64,
+    pub attack_per_minute: f64,
+    pub keys_per_piece: f64,
+    // attack and cleared garbage per second, times 100
+    pub vs_score: f64,
+}
+
+impl StatLine {
+    fn new(totals: &Sample, time: Duration) -> StatLine {
+        let seconds = time.as_secs_f64();
+        let per_second = |count: usize| {
+            if seconds > 0.0 {
+                count as f64 / seconds
+            } else {
+                0.0
+            }
+        };
+        StatLine {
+            pieces_per_second: per_second(totals.pieces),
+            attack_per_minute: per_second(totals.attack) * 60.0,
+            keys_per_piece: if totals.pieces > 0 {
+                totals.keys as f64 / totals.pieces as f64
+            } else {
+                0.0
+            },
+            vs_score: per_second(totals.attack + totals.garbage_cleared) * 100.0,
+        }
+    }
+}
+
+// pieces locked, lines cleared, garbage sent and keys pressed as a game goes, as rates over the
+// whole game and over the last ROLLING_WINDOW; the clock is game time, moved on with advance
+// while the game runs and stopped for good at game over, so pauses don't count
+pub struct LiveStats {
+    played: Duration,
+    over: bool,
+    totals: Sample,
+    // what each observation added at what time played, newest last, no older than ROLLING_WINDOW
+    recent: VecDeque<(Duration, Sample)>,
+}
+
+impl Default for LiveStats {
+    fn default() -> Self {
+        LiveStats::new()
+    }
+}
+
+impl LiveStats {
+    pub fn new() -> LiveStats {
+        LiveStats {
+            played: Duration::ZERO,
+            over: false,
+            totals: Sample::default(),
+            recent: VecDeque::new(),
+        }
+    }
+
+    // takes in the events of a step of the game and the keys pressed for it, keys only count
+    // when they play the game
+    pub fn observe(&mut self, events: &[GameEvent], inputs: &InputEvent, keys: &KeyMap) {
+        let now = self.played;
+        let game_keys = [
+            keys.left,
+            keys.right,
+            keys.rotate_clockwise,
+            keys.rotate_counter_clockwise,
+            keys.soft_drop,
+            keys.hard_drop,
+            keys.hold,
+        ];
+        let mut step = Sample {
+            keys: inputs
+                .keys
+                .iter()
+                .filter(|key| game_keys.contains(key))
+                .count(),
+            ..Sample::default()
+        };
+        for event in events {
+            match event {
+                GameEvent::Locked { .. } => step.pieces += 1,
+                GameEvent::LinesCleared {
+                    rows,
+                    garbage,
+                    attack,
+                    ..
+                } => {
+                    step.lines += rows.len();
+                    step.garbage_cleared += garbage;
+                    step.attack += attack;
+                }
+                GameEvent::GameOver => self.over = true,
+                _ => {}
+            }
+        }
+        if step != Sample::default() {
+            self.totals.add(&step);
+            self.recent.push_back((now, step));
+        }
+        while self
+            .recent
+            .front()
+            .is_some_and(|(time, _)| now - *time >= ROLLING_WINDOW)
+        {
+            self.recent.pop_front();
+        }
+    }
+
+    // the game time that passed, nothing more once the game is over
+    pub fn advance(&mut self, time: Duration) {
+        if !self.over {
+            self.played += time;
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.played
+    }
+    pub fn lines(&self) -> usize {
+        self.totals.lines
+    }
+
+    pub fn whole_game(&self) -> StatLine {
+        StatLine::new(&self.totals, self.elapsed())
+    }
+
+    // the last ROLLING_WINDOW, or the whole game while it is shorter
+    pub fn rolling(&self) -> StatLine {
+        let mut totals = Sample::default();
+        for (_, step) in &self.recent {
+            totals.add(step);
+        }
+        StatLine::new(&totals, self.elapsed().min(ROLLING_WINDOW))
+    }
+}
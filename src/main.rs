@@ -1,19 +1,52 @@
+use std::thread::{self, JoinHandle};
+
 use macroquad::{color::Color, prelude::*};
-use tetrs::{process_logic, GameState, InputEvent, ActiveTetramino, PlacedBlocks, PlayfieldSize};
+use tetrs::{
+    ActiveTetramino, GARBAGE_COLOR, GameState, InputEvent, KeyMap, LockRecord, PlacedBlocks,
+    Position, TetraminoKind,
+    animation::{Animations, Effect},
+    audio::Audio,
+    bot::{Bot, Weights},
+    diagram::{Diagram, parse_queue, piece_char},
+    editor::BoardEditor,
+    events,
+    live_stats::LiveStats,
+    modes::{
+        BLITZ_DURATION, DEFAULT_DIG_ROWS, DEFAULT_GARBAGE_INTERVAL_MS, DEFAULT_MESSINESS,
+        DEFAULT_SPRINT_LINES, Dig, GameMode, Marathon, ModeStatus, Opener, Practice, Sprint,
+        TimeAttack, ULTRA_DURATION, format_duration,
+    },
+    net::{Connection, Host, NetError, NetStatus, NetVersus},
+    pc_solver::{DEFAULT_MAX_HEIGHT, Solution, solve},
+    process_logic_with_handling,
+    records::{Leaderboard, Record, Records, RecordsError, Replay, format_date},
+    ruleset::Ruleset,
+    scoring::SpinKind,
+    settings::{
+        DefaultMode, Handling, KEY_ACTIONS, SETTINGS_FILE, Settings, SettingsProblem, Skin,
+        binding, binding_mut, key_name,
+    },
+    spectate::Broadcaster,
+    tbp::TbpBot,
+    versus::{DEFAULT_FIRST_TO, Versus, VersusStatus},
+};
 
 fn draw_current_tetramino(cur_tetramino: &ActiveTetramino, grid_painter: &SquareBitGridPainter) {
     for block in &cur_tetramino.get_blocks_with_offset() {
-        grid_painter.draw_grid_cell(
-            block.coordinates.row,
-            block.coordinates.col,
-            block.color,
-        );
+        grid_painter.draw_grid_cell(block.coordinates.row, block.coordinates.col, block.color);
     }
 }
 
-fn draw_placed_blocks(placed: &PlacedBlocks, grid_painter: &SquareBitGridPainter) {
+// rows a line clear animation is dissolving are left to it
+fn draw_placed_blocks(
+    placed: &PlacedBlocks,
+    grid_painter: &SquareBitGridPainter,
+    dissolving: &[isize],
+) {
     for block in placed.get_blocks() {
-        grid_painter.draw_grid_cell(block.coordinates.row, block.coordinates.col, block.color);
+        if !dissolving.contains(&block.coordinates.row) {
+            grid_painter.draw_grid_cell(block.coordinates.row, block.coordinates.col, block.color);
+        }
     }
 }
 
@@ -29,7 +62,10 @@ struct GridSize {
 
 struct SquareBitGridPainter {
     grid_size: GridSize,
+    // rows at the top without a grid drawn, the origin is where the first visible row starts
+    hidden_rows: isize,
     deactivated_color: Color,
+    skin: Skin,
     origin: UIPosition,
     cell_size: f32,
     grid_spacing: f32,
@@ -45,13 +81,26 @@ impl SquareBitGridPainter {
     ) -> Self {
         SquareBitGridPainter {
             grid_size: size,
+            hidden_rows: 0,
             deactivated_color: default_color,
+            skin: Skin::default(),
             origin: position_origin,
             cell_size,
             grid_spacing: cells_spacing,
         }
     }
 
+    fn with_skin(mut self, skin: Skin) -> Self {
+        self.skin = skin;
+        self
+    }
+
+    fn with_hidden_rows(mut self, hidden_rows: isize) -> Self {
+        self.hidden_rows = hidden_rows;
+        self.origin.y -= hidden_rows as f32 * (self.cell_size + self.grid_spacing);
+        self
+    }
+
     pub fn cell_origin(&self, row: isize, col: isize) -> UIPosition {
         UIPosition {
             x: col as f32 * self.cell_size + col as f32 * self.grid_spacing + self.origin.x,
@@ -60,7 +109,7 @@ impl SquareBitGridPainter {
     }
 
     pub fn draw_empty_grid(&self) {
-        for r in 0..self.grid_size.rows {
+        for r in self.hidden_rows..self.grid_size.rows {
             for c in 0..self.grid_size.cols {
                 draw_rectangle(
                     self.cell_origin(r, c).x,
@@ -73,6 +122,15 @@ impl SquareBitGridPainter {
         }
     }
 
+    // the cell under a point, None outside the grid
+    pub fn cell_at(&self, x: f32, y: f32) -> Option<(isize, isize)> {
+        let pitch = self.cell_size + self.grid_spacing;
+        let row = ((y - self.origin.y) / pitch).floor() as isize;
+        let col = ((x - self.origin.x) / pitch).floor() as isize;
+        ((0..self.grid_size.rows).contains(&row) && (0..self.grid_size.cols).contains(&col))
+            .then_some((row, col))
+    }
+
     pub fn draw_grid_cell(&self, row: isize, col: isize, color: Color) {
         let cell_origin = UIPosition {
             x: col as f32 * self.cell_size + col as f32 * self.grid_spacing + self.origin.x,
@@ -83,39 +141,1416 @@ impl SquareBitGridPainter {
             cell_origin.y,
             self.cell_size,
             self.cell_size,
-            color,
+            self.skin.color(color),
         );
     }
 }
 
-fn draw_game_frame(game_state: &GameState) {
-    let game_grid_painter = SquareBitGridPainter::new(
+fn board_painter(
+    game_state: &GameState,
+    settings: &Settings,
+    origin: UIPosition,
+) -> SquareBitGridPainter {
+    SquareBitGridPainter::new(
         GridSize {
-            rows: game_state.playfield_size.rows,
-            cols: game_state.playfield_size.cols,
+            rows: game_state.playfield_size().rows,
+            cols: game_state.playfield_size().cols,
         },
         GRAY,
-        UIPosition { x: 50., y: 50. },
-        10.0,
-        5.0,
-    );
+        origin,
+        settings.cell_size,
+        settings.grid_spacing,
+    )
+    .with_skin(settings.skin)
+    .with_hidden_rows(game_state.playfield().hidden_rows())
+}
+
+// where the text next to a board drawn at x = 50 starts
+fn side_panel_x(game_state: &GameState, settings: &Settings) -> f32 {
+    let pitch = settings.cell_size + settings.grid_spacing;
+    50. + game_state.playfield_size().cols as f32 * pitch + 20.
+}
+
+fn draw_board(game_state: &GameState, settings: &Settings, origin: UIPosition) {
+    draw_board_dissolving(game_state, settings, origin, &[]);
+}
+
+fn draw_board_dissolving(
+    game_state: &GameState,
+    settings: &Settings,
+    origin: UIPosition,
+    dissolving: &[isize],
+) {
+    let game_grid_painter = board_painter(game_state, settings, origin);
     game_grid_painter.draw_empty_grid();
-    draw_placed_blocks(&game_state.placed_blocks, &game_grid_painter);
-    draw_current_tetramino(&game_state.current_tetramino, &game_grid_painter);
+    draw_placed_blocks(game_state.placed_blocks(), &game_grid_painter, dissolving);
+    // during a line clear the last piece is part of the board and the next one isn't out yet
+    if game_state.clearing_rows().is_empty() {
+        draw_current_tetramino(game_state.current_tetramino(), &game_grid_painter);
+    }
+    draw_garbage_meter(game_state, &game_grid_painter);
+}
+
+// incoming garbage as a bar growing up from the bottom left of the board
+fn draw_garbage_meter(game_state: &GameState, grid_painter: &SquareBitGridPainter) {
+    let incoming = game_state
+        .incoming_garbage()
+        .min(grid_painter.grid_size.rows as usize) as isize;
+    if incoming == 0 {
+        return;
+    }
+    let bottom = grid_painter.cell_origin(grid_painter.grid_size.rows, 0);
+    let top = grid_painter.cell_origin(grid_painter.grid_size.rows - incoming, 0);
+    draw_rectangle(
+        bottom.x - grid_painter.cell_size,
+        top.y,
+        grid_painter.cell_size / 2.,
+        bottom.y - top.y - grid_painter.grid_spacing,
+        ORANGE,
+    );
+}
+
+// outlines cells still to be filled, in the color of the piece meant for them
+fn draw_target<'a>(
+    game_state: &GameState,
+    settings: &Settings,
+    cells: impl IntoIterator<Item = (&'a Position, &'a TetraminoKind)>,
+    origin: UIPosition,
+) {
+    let painter = board_painter(game_state, settings, origin);
+    for (cell, kind) in cells {
+        let corner = painter.cell_origin(cell.row, cell.col);
+        draw_rectangle_lines(
+            corner.x - 1.,
+            corner.y - 1.,
+            painter.cell_size + 2.,
+            painter.cell_size + 2.,
+            2.,
+            kind.color(),
+        );
+    }
+}
+
+// the perfect clear hint, searched for off the frame loop: a search can take seconds
+enum PcHint {
+    Searching(JoinHandle<Option<Solution>>),
+    Found(Option<Solution>),
+}
+
+// looks for the first perfect clear the current piece, hold and the preview the player sees
+// allow
+fn find_perfect_clear(game_state: &GameState) -> PcHint {
+    let playfield = game_state.playfield().clone();
+    let rotation = game_state.ruleset().rotation;
+    let current = game_state.current_tetramino().kind();
+    let queue = game_state.next_queue();
+    let hold = game_state.hold_kind();
+    PcHint::Searching(thread::spawn(move || {
+        solve(
+            &playfield,
+            rotation,
+            current,
+            &queue,
+            hold,
+            DEFAULT_MAX_HEIGHT,
+            1,
+        )
+        .into_iter()
+        .next()
+    }))
+}
+
+fn draw_queue(game_state: &GameState, origin: UIPosition) {
+    let next: String = game_state
+        .next_queue()
+        .into_iter()
+        .map(piece_char)
+        .collect();
+    let mut text = format!("next: {next}");
+    if game_state.ruleset().hold {
+        text.push_str(&format!(
+            "  hold: {}",
+            game_state.hold_kind().map_or('-', piece_char)
+        ));
+    }
+    draw_text(&text, origin.x, origin.y, 20., WHITE);
+}
+
+// where a board drawn at y = 50 ends
+fn board_bottom(game_state: &GameState, settings: &Settings) -> f32 {
+    let pitch = settings.cell_size + settings.grid_spacing;
+    let visible_rows = game_state.playfield_size().rows - game_state.playfield().hidden_rows();
+    50. + visible_rows as f32 * pitch
+}
+
+// what the engine's events set going over a board drawn at origin
+fn draw_animations(
+    game_state: &GameState,
+    settings: &Settings,
+    animations: &Animations,
+    origin: UIPosition,
+) {
+    let painter = board_painter(game_state, settings, origin);
+    let pitch = painter.cell_size + painter.grid_spacing;
+    let visible = |row: isize| row >= painter.hidden_rows;
+    let top = painter.cell_origin(painter.hidden_rows, 0);
+    let mut popup_y = top.y + (painter.grid_size.rows - painter.hidden_rows) as f32 * pitch / 3.;
+    for animation in animations.running() {
+        let left = 1. - animation.progress();
+        match &animation.effect {
+            // every cleared cell shrinks into its middle as it fades
+            Effect::LineClear { cells, .. } => {
+                let size = painter.cell_size * left;
+                let inset = (painter.cell_size - size) / 2.;
+                for block in cells.iter().filter(|block| visible(block.coordinates.row)) {
+                    let corner = painter.cell_origin(block.coordinates.row, block.coordinates.col);
+                    draw_rectangle(
+                        corner.x + inset,
+                        corner.y + inset,
+                        size,
+                        size,
+                        Color {
+                            a: left,
+                            ..painter.skin.color(block.color)
+                        },
+                    );
+                }
+            }
+            Effect::LockFlash { cells } => {
+                for cell in cells.iter().filter(|cell| visible(cell.row)) {
+                    let corner = painter.cell_origin(cell.row, cell.col);
+                    draw_rectangle(
+                        corner.x,
+                        corner.y,
+                        painter.cell_size,
+                        painter.cell_size,
+                        Color {
+                            a: 0.6 * left,
+                            ..WHITE
+                        },
+                    );
+                }
+            }
+            // a streak above the highest cell of every column the piece fell through
+            Effect::Trail { cells, rows } => {
+                let tops = cells.iter().filter(|cell| {
+                    cells
+                        .iter()
+                        .all(|other| other.col != cell.col || other.row >= cell.row)
+                });
+                for cell in tops {
+                    let from = (cell.row - *rows as isize).max(painter.hidden_rows);
+                    let start = painter.cell_origin(from, cell.col);
+                    let end = painter.cell_origin(cell.row, cell.col);
+                    draw_rectangle(
+                        start.x,
+                        start.y,
+                        painter.cell_size,
+                        end.y - start.y,
+                        Color {
+                            a: 0.3 * left,
+                            ..WHITE
+                        },
+                    );
+                }
+            }
+            Effect::Popup { text } => {
+                let rise = 20. * animation.progress();
+                draw_text(
+                    text,
+                    top.x,
+                    popup_y - rise,
+                    24.,
+                    Color { a: left, ..YELLOW },
+                );
+                popup_y += 26.;
+            }
+            Effect::Shake { .. } => {}
+        }
+    }
+}
+
+fn draw_game_frame(game_state: &GameState, settings: &Settings, animations: &Animations) {
+    // a shaking board takes its animations along, the text around it stays put
+    let pitch = settings.cell_size + settings.grid_spacing;
+    let (shake_x, shake_y) = animations.shake_offset();
+    let origin = || UIPosition {
+        x: 50. + shake_x * pitch,
+        y: 50. + shake_y * pitch,
+    };
+    draw_board_dissolving(game_state, settings, origin(), animations.clearing_rows());
+    draw_animations(game_state, settings, animations, origin());
+    draw_queue(
+        game_state,
+        UIPosition {
+            x: 50.,
+            y: board_bottom(game_state, settings) + 25.,
+        },
+    );
+    draw_finesse(
+        game_state,
+        UIPosition {
+            x: side_panel_x(game_state, settings),
+            y: 60.,
+        },
+    );
+}
+
+// the settings that aren't key bindings, in menu order
+const SETTINGS_ENTRIES: [&str; 9] = [
+    "das_ms",
+    "arr_ms",
+    "soft_drop_factor",
+    "volume",
+    "muted",
+    "skin",
+    "cell_size",
+    "grid_spacing",
+    "default_mode",
+];
+
+fn menu_entries() -> impl Iterator<Item = &'static str> {
+    KEY_ACTIONS.into_iter().chain(SETTINGS_ENTRIES)
+}
+
+// the item of `all` `step` places away from `current`, wrapping around
+fn cycle<T: Copy + PartialEq>(all: &[T], current: T, step: isize) -> T {
+    let i = all.iter().position(|item| *item == current).unwrap_or(0) as isize;
+    all[(i + step).rem_euclid(all.len() as isize) as usize]
+}
+
+// F1 over a game: Up/Down pick an entry, Left/Right change it, Enter rebinds a key
+struct SettingsMenu {
+    selected: usize,
+    rebinding: bool,
+    // what went wrong loading or saving the settings
+    problems: Vec<String>,
+}
+
+impl SettingsMenu {
+    fn new(problems: &[SettingsProblem]) -> Self {
+        SettingsMenu {
+            selected: 0,
+            rebinding: false,
+            problems: problems.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    fn update(&mut self, settings: &mut Settings) {
+        let entry = menu_entries().nth(self.selected).unwrap();
+        if self.rebinding {
+            // Escape gives up on the new binding
+            if let Some(key) = get_last_key_pressed() {
+                if key != KeyCode::Escape {
+                    *binding_mut(&mut settings.keys, entry).unwrap() = key;
+                }
+                self.rebinding = false;
+            }
+            return;
+        }
+        let entries = menu_entries().count();
+        if is_key_pressed(KeyCode::Down) {
+            self.selected = (self.selected + 1) % entries;
+        }
+        if is_key_pressed(KeyCode::Up) {
+            self.selected = (self.selected + entries - 1) % entries;
+        }
+        if is_key_pressed(KeyCode::Enter) && KEY_ACTIONS.contains(&entry) {
+            self.rebinding = true;
+        }
+        let step = is_key_pressed(KeyCode::Right) as isize - is_key_pressed(KeyCode::Left) as isize;
+        if step == 0 {
+            return;
+        }
+        let adjust = |value: usize, by: isize, max: isize| {
+            (value as isize + step * by).clamp(0, max) as usize
+        };
+        let handling = &mut settings.handling;
+        match entry {
+            "das_ms" => handling.das_ms = adjust(handling.das_ms, 10, 1000),
+            "arr_ms" => handling.arr_ms = adjust(handling.arr_ms, 5, 1000),
+            "soft_drop_factor" => {
+                handling.soft_drop_factor = adjust(handling.soft_drop_factor, 5, 100)
+            }
+            "volume" => settings.volume = (settings.volume + step as f32 * 0.1).clamp(0., 1.),
+            "muted" => settings.muted = !settings.muted,
+            "skin" => settings.skin = cycle(&Skin::ALL, settings.skin, step),
+            "cell_size" => settings.cell_size = (settings.cell_size + step as f32).clamp(2., 64.),
+            "grid_spacing" => {
+                settings.grid_spacing = (settings.grid_spacing + step as f32).clamp(0., 32.)
+            }
+            "default_mode" => {
+                settings.default_mode = cycle(&DefaultMode::ALL, settings.default_mode, step)
+            }
+            _ => {}
+        }
+    }
+
+    fn draw(&self, settings: &Settings) {
+        draw_rectangle(30., 20., 500., 560., Color::new(0., 0., 0., 0.9));
+        let value = |entry: &str| match entry {
+            "das_ms" => format!("{} ms", settings.handling.das_ms),
+            "arr_ms" => match settings.handling.arr_ms {
+                0 => "instant".to_string(),
+                arr => format!("{arr} ms"),
+            },
+            "soft_drop_factor" => match settings.handling.soft_drop_factor {
+                0 => "instant".to_string(),
+                factor => format!("{factor}x"),
+            },
+            "volume" => format!("{:.0}%", settings.volume * 100.),
+            "muted" => if settings.muted { "yes" } else { "no" }.to_string(),
+            "skin" => format!("{:?}", settings.skin),
+            "cell_size" => format!("{}", settings.cell_size),
+            "grid_spacing" => format!("{}", settings.grid_spacing),
+            "default_mode" => format!("{:?}", settings.default_mode),
+            action => binding(&settings.keys, action).map_or(String::new(), key_name),
+        };
+        let mut y = 50.;
+        draw_text("settings, F1 saves and closes", 50., y, 24., WHITE);
+        for (i, entry) in menu_entries().enumerate() {
+            y += 22.;
+            let selected = i == self.selected;
+            let text = if selected && self.rebinding {
+                format!("{entry}: press a key")
+            } else {
+                format!("{entry}: {}", value(entry))
+            };
+            draw_text(&text, 50., y, 20., if selected { YELLOW } else { WHITE });
+        }
+        y += 30.;
+        draw_text(
+            "Up/Down select, Left/Right change, Enter rebinds",
+            50.,
+            y,
+            18.,
+            GRAY,
+        );
+        for problem in &self.problems {
+            y += 20.;
+            draw_text(problem, 50., y, 18., RED);
+        }
+    }
+}
+
+// the keys a player pressed this frame; skip hands out free pieces, so a match ignores it
+fn versus_inputs(keys: &KeyMap) -> InputEvent {
+    let mut pressed = get_keys_pressed();
+    pressed.remove(&keys.skip);
+    InputEvent {
+        keys: pressed,
+        held: get_keys_down(),
+    }
+}
+
+async fn run_versus(first_to: usize, ruleset: Ruleset, settings: &Settings) {
+    let new_match = || Versus::new(first_to, ruleset.clone());
+    let key_maps = [&settings.keys, &settings.keys_p2];
+    let mut versus = new_match();
+
+    loop {
+        let status = versus.status();
+        if status == VersusStatus::Playing {
+            for (player, keys) in versus.players_mut().iter_mut().zip(key_maps) {
+                let inputs = versus_inputs(keys);
+                process_logic_with_handling(player, inputs, keys, &settings.handling);
+            }
+            versus.update();
+        } else if is_key_pressed(KeyCode::R) {
+            match status {
+                VersusStatus::MatchOver { .. } => versus = new_match(),
+                _ => versus.next_round(),
+            }
+        }
+
+        clear_background(BLACK);
+        for (i, player) in versus.players().iter().enumerate() {
+            let x = 50. + i as f32 * (side_panel_x(player, settings) + 130.);
+            draw_board(player, settings, UIPosition { x, y: 50. });
+            draw_text(
+                &format!(
+                    "P{}  wins: {}/{}  score: {}",
+                    i + 1,
+                    versus.wins()[i],
+                    versus.first_to(),
+                    player.score()
+                ),
+                x,
+                40.,
+                20.,
+                WHITE,
+            );
+        }
+        let message = match versus.status() {
+            VersusStatus::Playing => None,
+            VersusStatus::RoundOver {
+                winner: Some(winner),
+            } => Some(format!(
+                "round to P{}, press R for the next one",
+                winner + 1
+            )),
+            VersusStatus::RoundOver { winner: None } => {
+                Some("draw, press R for the next round".to_string())
+            }
+            VersusStatus::MatchOver { winner } => Some(format!(
+                "P{} wins the match, press R for a rematch",
+                winner + 1
+            )),
+        };
+        if let Some(message) = message {
+            draw_text(&message, 50., 380., 24., WHITE);
+        }
+        draw_fps();
+        next_frame().await;
+    }
+}
+
+const BRUSH_KEYS: [(KeyCode, Option<TetraminoKind>); 8] = [
+    (KeyCode::Key1, Some(TetraminoKind::I)),
+    (KeyCode::Key2, Some(TetraminoKind::L)),
+    (KeyCode::Key3, Some(TetraminoKind::J)),
+    (KeyCode::Key4, Some(TetraminoKind::S)),
+    (KeyCode::Key5, Some(TetraminoKind::Z)),
+    (KeyCode::Key6, Some(TetraminoKind::O)),
+    (KeyCode::Key7, Some(TetraminoKind::T)),
+    (KeyCode::Key8, None),
+];
+const QUEUE_KEYS: [(KeyCode, TetraminoKind); 7] = [
+    (KeyCode::I, TetraminoKind::I),
+    (KeyCode::L, TetraminoKind::L),
+    (KeyCode::J, TetraminoKind::J),
+    (KeyCode::S, TetraminoKind::S),
+    (KeyCode::Z, TetraminoKind::Z),
+    (KeyCode::O, TetraminoKind::O),
+    (KeyCode::T, TetraminoKind::T),
+];
+
+// --editor [FILE]: paint a board, set its queue and hold, then play it in practice mode
+async fn run_editor(path: Option<&String>, ruleset: Ruleset, settings: &Settings) {
+    let size = ruleset.size();
+    let mut editor = BoardEditor::new(size);
+    let mut message = String::new();
+    if let Some(path) = path {
+        match std::fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| Diagram::parse(&text).map_err(|error| error.to_string()))
+        {
+            Ok(diagram) => editor.load(&diagram),
+            Err(error) => message = format!("can't load {path}: {error}"),
+        }
+    }
+    let origin = UIPosition { x: 50., y: 50. };
+    let painter = SquareBitGridPainter::new(
+        GridSize {
+            rows: size.rows,
+            cols: size.cols,
+        },
+        GRAY,
+        origin,
+        settings.cell_size,
+        settings.grid_spacing,
+    )
+    .with_skin(settings.skin);
+    // the game being played from the board, Escape goes back to editing
+    let mut playing: Option<(GameState, GameMode)> = None;
+    let mut status = ModeStatus::Playing;
+
+    loop {
+        clear_background(BLACK);
+        if let Some((game_state, mode)) = playing.as_mut() {
+            if is_key_pressed(KeyCode::Escape) {
+                playing = None;
+                continue;
+            }
+            if let GameMode::Practice(practice) = mode {
+                let changed = (is_key_pressed(KeyCode::Z) && practice.undo(game_state))
+                    || (is_key_pressed(KeyCode::X) && practice.redo(game_state));
+                if changed {
+                    status = mode.update(game_state);
+                }
+            }
+            if status == ModeStatus::Playing {
+                let inputs = InputEvent {
+                    keys: get_keys_pressed(),
+                    held: get_keys_down(),
+                };
+                let (keys, handling) = (&settings.keys, &settings.handling);
+                process_logic_with_handling(game_state, inputs, keys, handling);
+                status = mode.update(game_state);
+            } else if is_key_pressed(KeyCode::R) {
+                *game_state = editor.start(ruleset.new_game().with_finesse());
+                *mode = GameMode::Practice(Practice::default());
+                status = mode.update(game_state);
+            }
+            let side_x = side_panel_x(game_state, settings);
+            draw_game_frame(game_state, settings, &Animations::new());
+            draw_mode(game_state, mode, status, UIPosition { x: side_x, y: 120. });
+            draw_text("Esc back to the editor", side_x, 40., 20., WHITE);
+            draw_fps();
+            next_frame().await;
+            continue;
+        }
+
+        let (x, y) = mouse_position();
+        if let Some((row, col)) = painter.cell_at(x, y) {
+            if is_mouse_button_down(MouseButton::Left) {
+                editor.paint(Position::new(row, col));
+            } else if is_mouse_button_down(MouseButton::Right) {
+                editor.erase(Position::new(row, col));
+            }
+        }
+        for (key, brush) in BRUSH_KEYS {
+            if is_key_pressed(key) {
+                editor.set_brush(brush);
+            }
+        }
+        for (key, kind) in QUEUE_KEYS {
+            if is_key_pressed(key) {
+                editor.push_queue(kind);
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            editor.pop_queue();
+        }
+        if is_key_pressed(KeyCode::H) {
+            // cycles through no hold and every piece
+            let kinds: Vec<Option<TetraminoKind>> =
+                BRUSH_KEYS.iter().map(|(_, kind)| *kind).collect();
+            let current = kinds
+                .iter()
+                .position(|kind| *kind == editor.hold())
+                .unwrap_or(0);
+            editor.set_hold(kinds[(current + 1) % kinds.len()]);
+        }
+        if is_key_pressed(KeyCode::C) {
+            editor.clear();
+        }
+        if is_key_pressed(KeyCode::E) {
+            let diagram = editor.to_diagram();
+            let fumen = diagram.to_fumen();
+            print!("{}", diagram.to_text());
+            println!("{fumen}");
+            miniquad::window::clipboard_set(&fumen);
+            message = "fumen copied to the clipboard".to_string();
+        }
+        if is_key_pressed(KeyCode::V) {
+            message = match miniquad::window::clipboard_get().map(|text| Diagram::parse(&text)) {
+                Some(Ok(diagram)) => {
+                    editor.load(&diagram);
+                    "board pasted".to_string()
+                }
+                Some(Err(error)) => format!("can't paste: {error}"),
+                None => "the clipboard is empty".to_string(),
+            };
+        }
+        if is_key_pressed(KeyCode::W) {
+            message = match path {
+                Some(path) => match std::fs::write(path, editor.to_diagram().to_text()) {
+                    Ok(()) => format!("saved to {path}"),
+                    Err(error) => format!("can't save {path}: {error}"),
+                },
+                None => "start with --editor FILE to save".to_string(),
+            };
+        }
+        if is_key_pressed(KeyCode::Enter) {
+            let mut mode = GameMode::Practice(Practice::default());
+            let mut game_state = editor.start(ruleset.new_game().with_finesse());
+            status = mode.update(&mut game_state);
+            playing = Some((game_state, mode));
+            message.clear();
+        }
+
+        painter.draw_empty_grid();
+        for (position, kind) in editor.cells() {
+            let color = kind.map_or(GARBAGE_COLOR, TetraminoKind::color);
+            painter.draw_grid_cell(position.row, position.col, color);
+        }
+        let side_x = 50. + size.cols as f32 * (settings.cell_size + settings.grid_spacing) + 20.;
+        let piece_name = |kind: Option<TetraminoKind>| kind.map_or('-', piece_char);
+        let queue: String = editor
+            .queue()
+            .iter()
+            .map(|kind| piece_char(*kind))
+            .collect();
+        let lines = [
+            format!(
+                "brush: {}",
+                editor
+                    .brush()
+                    .map_or("garbage".to_string(), |kind| format!("{kind:?}"))
+            ),
+            format!("queue: {queue}"),
+            format!("hold: {}", piece_name(editor.hold())),
+            "left click paint, right click erase".to_string(),
+            "1-7 ILJSZOT brush, 8 garbage, C clear".to_string(),
+            "piece letters add to the queue, Backspace removes".to_string(),
+            "H cycles hold, Enter plays".to_string(),
+            "E copies fumen, V pastes fumen or text, W saves".to_string(),
+            message.clone(),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            draw_text(line, side_x, 60. + i as f32 * 20., 20., WHITE);
+        }
+        draw_fps();
+        next_frame().await;
+    }
+}
+
+// --host PORT waits for a peer, --connect ADDR joins one; the host picks seed and ruleset
+async fn run_net(args: &[String], settings: &Settings) -> Result<(), NetError> {
+    let value_after = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|i| args.get(i + 1))
+    };
+    let (connection, seed, ruleset) = if let Some(port) = value_after("--host") {
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        let host = Host::bind(format!("0.0.0.0:{port}"), seed, ruleset_from_args(args))?;
+        // the window keeps drawing while nobody has joined
+        loop {
+            if let Some(joined) = host.poll()? {
+                break joined;
+            }
+            clear_background(BLACK);
+            draw_text(
+                &format!("waiting for a peer on port {port}"),
+                50.,
+                50.,
+                20.,
+                WHITE,
+            );
+            next_frame().await;
+        }
+    } else {
+        let address = value_after("--connect").map_or("127.0.0.1:7878", String::as_str);
+        Connection::connect(address)?
+    };
+    let mut net = NetVersus::new(connection, seed, &ruleset);
+
+    loop {
+        if net.status() == NetStatus::Playing {
+            let (keys, handling) = (&settings.keys, &settings.handling);
+            process_logic_with_handling(net.local_mut(), versus_inputs(keys), keys, handling);
+        }
+        net.update()?;
+
+        clear_background(BLACK);
+        let remote_x = side_panel_x(net.local(), settings) + 180.;
+        draw_board(net.local(), settings, UIPosition { x: 50., y: 50. });
+        draw_board(
+            net.remote(),
+            settings,
+            UIPosition {
+                x: remote_x,
+                y: 50.,
+            },
+        );
+        draw_text(
+            &format!("you  score: {}", net.local().score()),
+            50.,
+            40.,
+            20.,
+            WHITE,
+        );
+        draw_text(
+            &format!("peer  score: {}", net.remote().score()),
+            remote_x,
+            40.,
+            20.,
+            WHITE,
+        );
+        let message = match net.status() {
+            NetStatus::Playing => None,
+            NetStatus::Won => Some("you win".to_string()),
+            NetStatus::Lost => Some("you lose".to_string()),
+            NetStatus::Desynced { piece } => Some(format!("boards desynced at piece {piece}")),
+        };
+        if let Some(message) = message {
+            draw_text(&message, 50., 380., 24., WHITE);
+        }
+        draw_fps();
+        next_frame().await;
+    }
+}
+
+// rates over the last seconds next to the ones over the whole game
+fn draw_live_stats(live_stats: &LiveStats, origin: UIPosition) {
+    let (rolling, whole) = (live_stats.rolling(), live_stats.whole_game());
+    let lines = [
+        format!("{:<4}{:>8}{:>8}", "", "now", "game"),
+        format!(
+            "{:<4}{:>8.2}{:>8.2}",
+            "PPS", rolling.pieces_per_second, whole.pieces_per_second
+        ),
+        format!(
+            "{:<4}{:>8.1}{:>8.1}",
+            "APM", rolling.attack_per_minute, whole.attack_per_minute
+        ),
+        format!(
+            "{:<4}{:>8.2}{:>8.2}",
+            "KPP", rolling.keys_per_piece, whole.keys_per_piece
+        ),
+        format!(
+            "{:<4}{:>8.1}{:>8.1}",
+            "VS", rolling.vs_score, whole.vs_score
+        ),
+    ];
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(line, origin.x, origin.y + i as f32 * 18., 18., WHITE);
+    }
+}
+
+fn draw_finesse(game_state: &GameState, origin: UIPosition) {
+    let Some(finesse) = game_state.finesse() else {
+        return;
+    };
+    draw_text(
+        &format!("finesse faults: {}/{}", finesse.faults(), finesse.pieces()),
+        origin.x,
+        origin.y,
+        20.,
+        WHITE,
+    );
+    if let Some(last) = finesse.last() {
+        let (text, color) = if last.is_fault() {
+            (
+                format!(
+                    "{:?}: +{} ({:?})",
+                    last.kind,
+                    last.extra_presses(),
+                    last.optimal
+                ),
+                RED,
+            )
+        } else {
+            (format!("{:?}: ok", last.kind), GREEN)
+        };
+        draw_text(&text, origin.x, origin.y + 20., 16., color);
+    }
+}
+
+fn draw_mode(game_state: &GameState, mode: &GameMode, status: ModeStatus, origin: UIPosition) {
+    let mut lines = vec![
+        mode.name().to_string(),
+        format!("score: {}", game_state.score()),
+        format!("level: {}", game_state.level()),
+    ];
+    match mode {
+        GameMode::Endless => lines.push(format!("lines: {}", game_state.lines_cleared())),
+        GameMode::Sprint(sprint) => {
+            lines.push(format!(
+                "lines: {}/{}",
+                game_state.lines_cleared().min(sprint.target_lines()),
+                sprint.target_lines()
+            ));
+            lines.push(format_duration(sprint.elapsed()));
+            for (i, split) in sprint.splits().iter().enumerate() {
+                lines.push(format!("{:>3}: {}", (i + 1) * 10, format_duration(*split)));
+            }
+            if let Some(best) = sprint.personal_best() {
+                lines.push(format!("best: {}", format_duration(best)));
+            }
+            if sprint.is_new_best() {
+                lines.push("new personal best!".to_string());
+            }
+        }
+        GameMode::Ultra(time_attack) | GameMode::Blitz(time_attack) => {
+            lines.push(format!("lines: {}", game_state.lines_cleared()));
+            lines.push(format!(
+                "time left: {}",
+                format_duration(time_attack.remaining())
+            ));
+            if status == ModeStatus::Finished {
+                lines.push(format!(
+                    "results: {} points, {} lines, level {} in {}",
+                    game_state.score(),
+                    game_state.lines_cleared(),
+                    game_state.level(),
+                    format_duration(time_attack.duration())
+                ));
+            }
+        }
+        GameMode::Dig(dig) => {
+            lines.push(format!(
+                "garbage left: {}/{}",
+                dig.remaining_lines(game_state),
+                dig.target_lines()
+            ));
+            lines.push(format_duration(dig.elapsed()));
+        }
+        GameMode::Opener(opener) => {
+            let (built, total) = opener.progress();
+            lines.push(opener.name().to_string());
+            lines.push(format!("built: {built}/{total}"));
+            if !opener.queue().is_empty() {
+                let queue: String = opener
+                    .queue()
+                    .iter()
+                    .map(|kind| piece_char(*kind))
+                    .collect();
+                lines.push(format!("queue: {queue}"));
+            }
+            lines.push(format!("deviations: {}", opener.deviations().len()));
+            lines.push("P shows a perfect clear".to_string());
+            if let Some(deviation) = opener.deviations().last() {
+                lines.push(format!(
+                    "piece {} ({:?}) is off the target",
+                    deviation.piece, deviation.kind
+                ));
+            }
+            if status == ModeStatus::Finished && opener.deviations().is_empty() {
+                lines.push("built cleanly!".to_string());
+            }
+        }
+        GameMode::Practice(practice) => {
+            lines.push(format!("lines: {}", game_state.lines_cleared()));
+            lines.push(format!(
+                "Z undo ({}), X redo ({})",
+                practice.undo_available(),
+                practice.redo_available()
+            ));
+            lines.push("P shows a perfect clear".to_string());
+        }
+        GameMode::Marathon(marathon) => {
+            lines.push(format!(
+                "lines: {}/{}",
+                game_state.lines_cleared(),
+                marathon.target_lines()
+            ));
+            lines.push(format_duration(marathon.elapsed()));
+            if status != ModeStatus::Playing {
+                let scoring = game_state.scoring();
+                lines.push(format!(
+                    "{:.2} pieces/s",
+                    marathon.pieces_per_second(game_state)
+                ));
+                lines.push(format!(
+                    "tetrises: {}",
+                    scoring.clear_count(SpinKind::None, 4)
+                ));
+                for (name, spin) in [("T-spin", SpinKind::Full), ("T-spin mini", SpinKind::Mini)] {
+                    lines.push(format!(
+                        "{name} 0/1/2/3: {}/{}/{}/{}",
+                        scoring.clear_count(spin, 0),
+                        scoring.clear_count(spin, 1),
+                        scoring.clear_count(spin, 2),
+                        scoring.clear_count(spin, 3)
+                    ));
+                }
+            }
+        }
+    }
+    match status {
+        ModeStatus::Playing => {}
+        ModeStatus::Finished => lines.push("finished, press R to restart".to_string()),
+        ModeStatus::ToppedOut => lines.push("topped out, press R to restart".to_string()),
+    }
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(line, origin.x, origin.y + i as f32 * 20., 20., WHITE);
+    }
+}
+
+// the mode given on the command line, the one from the settings without one
+fn mode_from_args(args: &[String], default_mode: DefaultMode) -> GameMode {
+    let value_after = |flag: &str| -> Option<Option<usize>> {
+        args.iter()
+            .position(|arg| arg == flag)
+            .map(|i| args.get(i + 1).and_then(|value| value.parse().ok()))
+    };
+    let seconds_or = |seconds: Option<usize>, default| {
+        seconds.map_or(default, |seconds| {
+            std::time::Duration::from_secs(seconds as u64)
+        })
+    };
+    if let Some(lines) = value_after("--sprint") {
+        GameMode::Sprint(lines.map_or_else(Sprint::default, Sprint::new))
+    } else if let Some(seconds) = value_after("--ultra") {
+        GameMode::Ultra(TimeAttack::new(seconds_or(seconds, ULTRA_DURATION)))
+    } else if let Some(seconds) = value_after("--blitz") {
+        GameMode::Blitz(TimeAttack::new(seconds_or(seconds, BLITZ_DURATION)))
+    } else if let Some(lines) = value_after("--dig") {
+        GameMode::Dig(lines.map_or_else(Dig::default, |lines| {
+            Dig::new(
+                lines,
+                DEFAULT_DIG_ROWS,
+                DEFAULT_MESSINESS,
+                DEFAULT_GARBAGE_INTERVAL_MS,
+            )
+        }))
+    } else if let Some(level) = value_after("--marathon") {
+        GameMode::Marathon(level.map_or_else(Marathon::default, Marathon::new))
+    } else if let Some(path) = args
+        .iter()
+        .position(|arg| arg == "--opener")
+        .and_then(|i| args.get(i + 1))
+    {
+        match load_opener(path, args) {
+            Ok(opener) => GameMode::Opener(opener),
+            Err(error) => {
+                eprintln!("can't load {path}: {error}");
+                GameMode::Endless
+            }
+        }
+    } else if let Some(limit) = value_after("--practice") {
+        GameMode::Practice(limit.map_or_else(Practice::default, Practice::new))
+    } else {
+        match default_mode {
+            DefaultMode::Endless => GameMode::Endless,
+            DefaultMode::Sprint => GameMode::Sprint(Sprint::default()),
+            DefaultMode::Ultra => GameMode::Ultra(TimeAttack::new(ULTRA_DURATION)),
+            DefaultMode::Blitz => GameMode::Blitz(TimeAttack::new(BLITZ_DURATION)),
+            DefaultMode::Marathon => GameMode::Marathon(Marathon::default()),
+            DefaultMode::Dig => GameMode::Dig(Dig::default()),
+            DefaultMode::Practice => GameMode::Practice(Practice::default()),
+        }
+    }
+}
+
+// a fumen or text board from a file, --queue PIECES replaces the queue it comes with
+fn load_opener(path: &str, args: &[String]) -> Result<Opener, Box<dyn std::error::Error>> {
+    let mut diagram = Diagram::parse(&std::fs::read_to_string(path)?)?;
+    if let Some(queue) = args
+        .iter()
+        .position(|arg| arg == "--queue")
+        .and_then(|i| args.get(i + 1))
+    {
+        diagram.queue = parse_queue(queue)?;
+    }
+    let name = std::path::Path::new(path)
+        .file_stem()
+        .map_or(path.into(), |stem| stem.to_string_lossy());
+    Ok(Opener::new(name, diagram))
+}
+
+// --ruleset FILE plays by the rules of a TOML file, fields it leaves out keep their default
+fn ruleset_from_args(args: &[String]) -> Ruleset {
+    let Some(path) = args
+        .iter()
+        .position(|arg| arg == "--ruleset")
+        .and_then(|i| args.get(i + 1))
+    else {
+        return Ruleset::default();
+    };
+    Ruleset::load(path).unwrap_or_else(|error| {
+        eprintln!("can't load {path}: {error}");
+        Ruleset::default()
+    })
+}
+
+// adds a game that just ended to the stats and to the leaderboard of its mode, saving a replay
+// when it made the board; practice and openers can be undone or replayed, so they don't count
+fn record_game(
+    records: &mut Records,
+    save: bool,
+    game_state: &GameState,
+    locks: Vec<LockRecord>,
+    mode: &GameMode,
+    status: ModeStatus,
+    played: std::time::Duration,
+) -> Option<String> {
+    if matches!(mode, GameMode::Practice(_) | GameMode::Opener(_)) {
+        return None;
+    }
+    records.stats_mut().add_game(game_state, played);
+    let board = match mode {
+        GameMode::Marathon(marathon) => Some((Leaderboard::Marathon, marathon.elapsed())),
+        GameMode::Ultra(time_attack) => Some((Leaderboard::Ultra, time_attack.elapsed())),
+        GameMode::Sprint(sprint) if status == ModeStatus::Finished => Some((
+            Leaderboard::Sprint {
+                lines: sprint.target_lines(),
+            },
+            sprint.elapsed(),
+        )),
+        _ => None,
+    };
+    let mut message = None;
+    if let Some((board, time)) = board {
+        let record = Record::new(game_state, time);
+        if let Some(rank) = records.rank(board, &record) {
+            let replay = Replay {
+                seed: game_state.seed(),
+                ruleset: game_state.ruleset().clone(),
+                locks,
+            };
+            let replay = replay
+                .save()
+                .map_err(|error| eprintln!("can't save the replay: {error}"))
+                .ok();
+            records.submit(board, record.with_replay(replay));
+            message = Some(format!("#{} on the {} leaderboard", rank + 1, mode.name()));
+        }
+    }
+    if save && let Err(error) = records.save() {
+        eprintln!("can't save records: {error}");
+    }
+    message
+}
+
+// lifetime totals and the top of every leaderboard of the ruleset played, opened with F2
+fn draw_stats(records: &Records, ruleset_hash: u64, error: Option<&RecordsError>) {
+    if let Some(error) = error {
+        draw_text(&error.to_string(), 50., 25., 20., RED);
+    }
+    let stats = records.stats();
+    let play_time = stats.play_time().as_secs();
+    let mut lines = vec![
+        "stats, F2 closes".to_string(),
+        format!("games: {}", stats.games),
+        format!("pieces: {}  lines: {}", stats.pieces, stats.lines),
+        format!(
+            "play time: {}h {:02}m",
+            play_time / 3600,
+            play_time / 60 % 60
+        ),
+        format!(
+            "{:.2} pieces/s  {:.1} attack/min",
+            stats.pieces_per_second(),
+            stats.attack_per_minute()
+        ),
+        format!("T-spin 0/1/2/3: {:?}", stats.t_spins.full),
+        format!("T-spin mini 0/1/2/3: {:?}", stats.t_spins.mini),
+    ];
+    let boards = [
+        ("Marathon", Leaderboard::Marathon),
+        ("Ultra", Leaderboard::Ultra),
+        (
+            "Sprint",
+            Leaderboard::Sprint {
+                lines: DEFAULT_SPRINT_LINES,
+            },
+        ),
+    ];
+    for (name, board) in boards {
+        lines.push(String::new());
+        lines.push(name.to_string());
+        let entries = records.leaderboard(board, ruleset_hash);
+        for (i, record) in entries.iter().take(5).enumerate() {
+            let result = match board {
+                Leaderboard::Sprint { .. } => format_duration(record.time()),
+                _ => record.score.to_string(),
+            };
+            lines.push(format!(
+                "{:>2}. {result}  {}{}",
+                i + 1,
+                format_date(record.date),
+                if record.replay.is_some() {
+                    "  (replay)"
+                } else {
+                    ""
+                }
+            ));
+        }
+    }
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(line, 50., 50. + i as f32 * 20., 20., WHITE);
+    }
+}
+
+fn new_game(args: &[String], settings: &Settings, records: &Records) -> (GameState, GameMode) {
+    let ruleset = ruleset_from_args(args);
+    let mut mode = match mode_from_args(args, settings.default_mode) {
+        GameMode::Sprint(sprint) => {
+            let board = Leaderboard::Sprint {
+                lines: sprint.target_lines(),
+            };
+            let best = records.best(board, ruleset.fingerprint());
+            GameMode::Sprint(sprint.with_personal_best(best.map(Record::time)))
+        }
+        mode => mode,
+    };
+    let game_state = ruleset.new_game().with_events().with_finesse();
+    let game_state = mode.configure(game_state);
+    (game_state, mode)
 }
 
 #[macroquad::main("MyGame")]
 async fn main() {
-    let mut game_state = GameState::new(PlayfieldSize { rows: 20, cols: 10 });
+    let args: Vec<String> = std::env::args().collect();
+    let (mut settings, mut problems) = Settings::load();
+    for problem in &problems {
+        eprintln!("settings: {problem}");
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--versus") {
+        let first_to = args
+            .get(i + 1)
+            .and_then(|wins| wins.parse().ok())
+            .unwrap_or(DEFAULT_FIRST_TO);
+        run_versus(first_to, ruleset_from_args(&args), &settings).await;
+        return;
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--editor") {
+        run_editor(args.get(i + 1), ruleset_from_args(&args), &settings).await;
+        return;
+    }
+    if args.iter().any(|arg| arg == "--host" || arg == "--connect") {
+        if let Err(error) = run_net(&args, &settings).await {
+            eprintln!("{error}");
+        }
+        return;
+    }
+    // records that didn't load show up on the stats screen, and nothing is saved over them
+    // unless the broken file was moved aside
+    let (mut records, records_error) = match Records::load() {
+        Ok(records) => (records, None),
+        Err(error) => {
+            eprintln!("{error}");
+            (Records::default(), Some(error))
+        }
+    };
+    let save_records = records_error
+        .as_ref()
+        .is_none_or(RecordsError::allows_saving);
+    let (mut game_state, mut mode) = new_game(&args, &settings, &records);
+    let mut status = ModeStatus::Playing;
+    let mut bot = std::env::args()
+        .any(|arg| arg == "--bot")
+        .then(|| Bot::new(Weights::default()));
+    // --broadcast PORT lets spectators on this machine follow this game with the spectate
+    // binary, --broadcast-public opens it to other machines too
+    let broadcast_host = if args.iter().any(|arg| arg == "--broadcast-public") {
+        "0.0.0.0"
+    } else {
+        "127.0.0.1"
+    };
+    let mut broadcaster = args
+        .iter()
+        .position(|arg| arg == "--broadcast")
+        .and_then(|i| args.get(i + 1))
+        .and_then(
+            |port| match Broadcaster::bind(format!("{broadcast_host}:{port}")) {
+                Ok(broadcaster) => Some(broadcaster),
+                Err(error) => {
+                    eprintln!("can't broadcast on port {port}: {error}");
+                    None
+                }
+            },
+        );
+    // --tbp "COMMAND [ARGS]" hands the game to an external Tetris Bot Protocol bot
+    let mut tbp = match args
+        .iter()
+        .position(|arg| arg == "--tbp")
+        .and_then(|i| args.get(i + 1))
+    {
+        Some(command) => {
+            let mut words = command.split_whitespace().map(str::to_string);
+            let program = words.next().unwrap_or_default();
+            match TbpBot::launch(&program, &words.collect::<Vec<_>>()) {
+                Ok(tbp) => Some(tbp),
+                Err(error) => {
+                    eprintln!("can't start {program}: {error}");
+                    return;
+                }
+            }
+        }
+        None => None,
+    };
+    // the perfect clear asked for with P, kept until the next piece locks
+    let mut pc_hint: Option<(usize, PcHint)> = None;
+    // F1 pauses the game under the settings menu, closing it saves them
+    let mut menu: Option<SettingsMenu> = None;
+    // F2 pauses the game under the stats screen
+    let mut show_stats = false;
+    // where the last game placed on its leaderboard
+    let mut record_message: Option<String> = None;
+    let mut started = std::time::Instant::now();
+    let mut live_stats = LiveStats::new();
+    // every lock of the game so far, for its replay
+    let mut locks = Vec::new();
+    let mut animations = Animations::new();
+    let mut audio = Audio::load(settings.volume, settings.muted).await;
+    audio.start_music();
 
     loop {
-        let inputs = InputEvent {
-            keys: get_keys_pressed(),
+        if is_key_pressed(KeyCode::F1) && !menu.as_ref().is_some_and(|menu| menu.rebinding) {
+            menu = match menu {
+                Some(_) => {
+                    // a saved file is a clean one, a failed save shows up next time the menu opens
+                    match settings.save() {
+                        Ok(()) => problems.clear(),
+                        Err(error) => {
+                            eprintln!("can't save settings: {error}");
+                            problems = vec![SettingsProblem {
+                                entry: SETTINGS_FILE.to_string(),
+                                message: error.to_string(),
+                            }];
+                        }
+                    }
+                    None
+                }
+                None => Some(SettingsMenu::new(&problems)),
+            };
+            if menu.is_none() {
+                game_state.restart_timers();
+            }
+        }
+        if let Some(menu) = menu.as_mut() {
+            menu.update(&mut settings);
+            audio.set_volume(settings.volume, settings.muted);
+            clear_background(BLACK);
+            draw_game_frame(&game_state, &settings, &animations);
+            menu.draw(&settings);
+            next_frame().await;
+            continue;
+        }
+        if is_key_pressed(KeyCode::F2) {
+            show_stats = !show_stats;
+            if !show_stats {
+                game_state.restart_timers();
+            }
+        }
+        if show_stats {
+            clear_background(BLACK);
+            draw_stats(
+                &records,
+                game_state.ruleset().fingerprint(),
+                records_error.as_ref(),
+            );
+            next_frame().await;
+            continue;
+        }
+
+        let inputs = match bot.as_mut() {
+            Some(bot) => bot.next_input(&game_state),
+            None => InputEvent {
+                keys: get_keys_pressed(),
+                held: get_keys_down(),
+            },
         };
 
-        process_logic(&mut game_state, inputs);
+        // undo works after a top out too, that is when it is needed most
+        if let GameMode::Practice(practice) = &mut mode {
+            let changed = (is_key_pressed(KeyCode::Z) && practice.undo(&mut game_state))
+                || (is_key_pressed(KeyCode::X) && practice.redo(&mut game_state));
+            if changed {
+                status = mode.update(&mut game_state);
+            }
+        }
+        // one search at a time, and none while rows are clearing with no piece in play
+        if matches!(mode, GameMode::Practice(_) | GameMode::Opener(_))
+            && is_key_pressed(KeyCode::P)
+            && !matches!(pc_hint, Some((_, PcHint::Searching(_))))
+            && game_state.clearing_rows().is_empty()
+        {
+            pc_hint = Some((game_state.pieces_placed(), find_perfect_clear(&game_state)));
+        }
+        if status == ModeStatus::Playing {
+            // the bot presses the default keys and wants them to act at once
+            let (keys, handling) = match bot {
+                Some(_) => (KeyMap::default(), Handling::default()),
+                None => (settings.keys.clone(), settings.handling),
+            };
+            live_stats.advance(std::time::Duration::from_secs_f32(get_frame_time()));
+            match tbp.as_mut() {
+                Some(tbp) => {
+                    if let Err(error) = tbp.play_piece(&mut game_state) {
+                        eprintln!("{error}");
+                        return;
+                    }
+                }
+                None => {
+                    process_logic_with_handling(&mut game_state, inputs.clone(), &keys, &handling)
+                }
+            }
+            let events = game_state.take_events();
+            locks.extend(events::lock_records(&events));
+            live_stats.observe(&events, &inputs, &keys);
+            audio.play(&events);
+            animations.observe(&events);
+            status = mode.update(&mut game_state);
+            if status != ModeStatus::Playing {
+                audio.stop_music();
+            }
+            // only the player's own games go on the records
+            if status != ModeStatus::Playing && bot.is_none() && tbp.is_none() {
+                record_message = record_game(
+                    &mut records,
+                    save_records,
+                    &game_state,
+                    std::mem::take(&mut locks),
+                    &mode,
+                    status,
+                    started.elapsed(),
+                );
+            }
+        } else if is_key_pressed(KeyCode::R) {
+            (game_state, mode) = new_game(&args, &settings, &records);
+            status = ModeStatus::Playing;
+            record_message = None;
+            started = std::time::Instant::now();
+            live_stats = LiveStats::new();
+            locks.clear();
+            audio.start_music();
+            animations = Animations::new();
+            if let Some(Err(error)) = tbp.as_mut().map(|tbp| tbp.start(&game_state)) {
+                eprintln!("{error}");
+                return;
+            }
+        }
+        if let Some(broadcaster) = broadcaster.as_mut() {
+            broadcaster.publish(&game_state);
+        }
+        animations.advance(std::time::Duration::from_secs_f32(get_frame_time()));
         clear_background(BLACK);
-        draw_game_frame(&game_state);
+        draw_game_frame(&game_state, &settings, &animations);
+        if let GameMode::Opener(opener) = &mode {
+            draw_target(
+                &game_state,
+                &settings,
+                opener.remaining(),
+                UIPosition { x: 50., y: 50. },
+            );
+        }
+        // a search the player moved on from is left to finish on its own
+        if pc_hint
+            .as_ref()
+            .is_some_and(|(pieces, _)| *pieces != game_state.pieces_placed())
+        {
+            pc_hint = None;
+        }
+        pc_hint = pc_hint.map(|(pieces, hint)| match hint {
+            PcHint::Searching(worker) if worker.is_finished() => {
+                (pieces, PcHint::Found(worker.join().unwrap_or(None)))
+            }
+            hint => (pieces, hint),
+        });
+        match &pc_hint {
+            Some((_, PcHint::Found(Some(solution)))) => {
+                let cells: Vec<(Position, TetraminoKind)> = solution
+                    .pieces
+                    .iter()
+                    .flat_map(|piece| piece.cells.iter().map(|cell| (*cell, piece.kind)))
+                    .collect();
+                let cells = cells.iter().map(|(cell, kind)| (cell, kind));
+                draw_target(&game_state, &settings, cells, UIPosition { x: 50., y: 50. });
+            }
+            Some((_, PcHint::Found(None))) => {
+                draw_text("no perfect clear found", 50., 30., 20., WHITE);
+            }
+            Some((_, PcHint::Searching(_))) => {
+                draw_text("looking for a perfect clear", 50., 30., 20., GRAY);
+            }
+            None => {}
+        }
+        if let Some(message) = &record_message {
+            draw_text(message, 50., 30., 20., YELLOW);
+        }
+        let side_x = side_panel_x(&game_state, &settings);
+        draw_mode(
+            &game_state,
+            &mode,
+            status,
+            UIPosition { x: side_x, y: 120. },
+        );
+        draw_text("F1 settings, F2 stats", side_x, 40., 20., GRAY);
+        draw_live_stats(
+            &live_stats,
+            UIPosition {
+                x: 50.,
+                y: board_bottom(&game_state, &settings) + 50.,
+            },
+        );
         draw_fps();
         next_frame().await;
     }
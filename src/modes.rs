@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+use crate::GameState;
+
+mod dig;
+mod marathon;
+mod opener;
+mod practice;
+mod sprint;
+mod time_attack;
+
+pub use dig::{
+    DEFAULT_DIG_LINES, DEFAULT_DIG_ROWS, DEFAULT_GARBAGE_INTERVAL_MS, DEFAULT_MESSINESS, Dig,
+};
+pub use marathon::{MARATHON_LINES, Marathon};
+pub use opener::{Deviation, Opener};
+pub use practice::{DEFAULT_UNDO_LIMIT, Practice};
+pub use sprint::{DEFAULT_SPRINT_LINES, Sprint};
+pub use time_attack::{BLITZ_DURATION, BLITZ_LINES_PER_LEVEL, TimeAttack, ULTRA_DURATION};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModeStatus {
+    Playing,
+    Finished,
+    ToppedOut,
+}
+
+pub enum GameMode {
+    Endless,
+    Sprint(Sprint),
+    Ultra(TimeAttack),
+    Blitz(TimeAttack),
+    Marathon(Marathon),
+    Dig(Dig),
+    Practice(Practice),
+    Opener(Opener),
+}
+
+impl GameMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Endless => "Endless",
+            GameMode::Sprint(_) => "Sprint",
+            GameMode::Ultra(_) => "Ultra",
+            GameMode::Blitz(_) => "Blitz",
+            GameMode::Marathon(_) => "Marathon",
+            GameMode::Dig(_) => "Dig",
+            GameMode::Practice(_) => "Practice",
+            GameMode::Opener(_) => "Opener",
+        }
+    }
+
+    // rules the mode needs from the engine before the first piece falls
+    pub fn configure(&mut self, mut game_state: GameState) -> GameState {
+        match self {
+            GameMode::Blitz(_) => game_state.with_level_progression(1, BLITZ_LINES_PER_LEVEL),
+            GameMode::Marathon(marathon) => game_state.with_level(marathon.start_level()),
+            GameMode::Dig(dig) => {
+                dig.fill(&mut game_state);
+                game_state
+            }
+            GameMode::Opener(opener) => opener.configure(game_state),
+            _ => game_state,
+        }
+    }
+
+    pub fn update(&mut self, game_state: &mut GameState) -> ModeStatus {
+        match self {
+            GameMode::Endless => {
+                if game_state.is_game_over() {
+                    ModeStatus::ToppedOut
+                } else {
+                    ModeStatus::Playing
+                }
+            }
+            GameMode::Sprint(sprint) => sprint.update(game_state),
+            GameMode::Ultra(time_attack) | GameMode::Blitz(time_attack) => {
+                time_attack.update(game_state)
+            }
+            GameMode::Marathon(marathon) => marathon.update(game_state),
+            GameMode::Dig(dig) => dig.update(game_state),
+            GameMode::Practice(practice) => practice.update(game_state),
+            GameMode::Opener(opener) => opener.update(game_state),
+        }
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
@@ -0,0 +1,112 @@
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::{GameState, TimerMs, modes::ModeStatus};
+
+pub const DEFAULT_DIG_LINES: usize = 18;
+pub const DEFAULT_DIG_ROWS: usize = 8;
+pub const DEFAULT_MESSINESS: f64 = 0.3;
+pub const DEFAULT_GARBAGE_INTERVAL_MS: usize = 5000;
+
+pub struct Dig {
+    target_lines: usize,
+    initial_rows: usize,
+    // chance the hole moves to another column from one garbage row to the next
+    messiness: f64,
+    garbage_timer: TimerMs,
+    hole: Option<isize>,
+    started: Instant,
+    finished: Option<Duration>,
+    status: ModeStatus,
+}
+
+impl Default for Dig {
+    fn default() -> Self {
+        Dig::new(
+            DEFAULT_DIG_LINES,
+            DEFAULT_DIG_ROWS,
+            DEFAULT_MESSINESS,
+            DEFAULT_GARBAGE_INTERVAL_MS,
+        )
+    }
+}
+
+impl Dig {
+    pub fn new(
+        target_lines: usize,
+        initial_rows: usize,
+        messiness: f64,
+        garbage_interval_ms: usize,
+    ) -> Dig {
+        Dig {
+            target_lines,
+            initial_rows,
+            // NaN would get through the clamp and make random_bool panic
+            messiness: if messiness.is_finite() {
+                messiness.clamp(0.0, 1.0)
+            } else {
+                DEFAULT_MESSINESS
+            },
+            garbage_timer: TimerMs::new(garbage_interval_ms),
+            hole: None,
+            started: Instant::now(),
+            finished: None,
+            status: ModeStatus::Playing,
+        }
+    }
+
+    pub fn fill(&mut self, game_state: &mut GameState) {
+        let holes = self.next_holes(game_state, self.initial_rows);
+        game_state.add_garbage_rows(&holes);
+        self.started = Instant::now();
+        self.garbage_timer = self.garbage_timer.reset();
+    }
+
+    pub fn update(&mut self, game_state: &mut GameState) -> ModeStatus {
+        if self.status != ModeStatus::Playing {
+            return self.status;
+        }
+        if self.garbage_timer.update() {
+            let holes = self.next_holes(game_state, 1);
+            game_state.add_garbage_rows(&holes);
+        }
+        if game_state.garbage_lines_cleared() >= self.target_lines {
+            self.status = ModeStatus::Finished;
+        } else if game_state.is_game_over() {
+            self.status = ModeStatus::ToppedOut;
+        }
+        if self.status != ModeStatus::Playing {
+            self.finished = Some(self.started.elapsed());
+        }
+        self.status
+    }
+
+    pub fn target_lines(&self) -> usize {
+        self.target_lines
+    }
+    pub fn remaining_lines(&self, game_state: &GameState) -> usize {
+        self.target_lines
+            .saturating_sub(game_state.garbage_lines_cleared())
+    }
+    pub fn elapsed(&self) -> Duration {
+        self.finished.unwrap_or_else(|| self.started.elapsed())
+    }
+
+    // holes for the next rows, listed top to bottom as they are pushed in
+    fn next_holes(&mut self, game_state: &mut GameState, rows: usize) -> Vec<isize> {
+        let cols = game_state.playfield_size().cols;
+        let rng = game_state.garbage_rng();
+        let mut holes = Vec::with_capacity(rows);
+        for _ in 0..rows {
+            let hole = match self.hole {
+                Some(hole) if cols < 2 || !rng.random_bool(self.messiness) => hole,
+                Some(hole) => (hole + rng.random_range(1..cols as i64) as isize) % cols,
+                None => rng.random_range(0..cols as i64) as isize,
+            };
+            self.hole = Some(hole);
+            holes.push(hole);
+        }
+        holes
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::{Duration, Instant};
+
+use crate::{GameState, modes::ModeStatus};
+
+pub const MARATHON_LINES: usize = 150;
+
+pub struct Marathon {
+    start_level: usize,
+    target_lines: usize,
+    started: Instant,
+    finished: Option<Duration>,
+    status: ModeStatus,
+}
+
+impl Default for Marathon {
+    fn default() -> Self {
+        Marathon::new(1)
+    }
+}
+
+impl Marathon {
+    pub fn new(start_level: usize) -> Marathon {
+        Marathon {
+            start_level: start_level.max(1),
+            target_lines: MARATHON_LINES,
+            started: Instant::now(),
+            finished: None,
+            status: ModeStatus::Playing,
+        }
+    }
+
+    pub fn update(&mut self, game_state: &GameState) -> ModeStatus {
+        if self.status != ModeStatus::Playing {
+            return self.status;
+        }
+        if game_state.lines_cleared() >= self.target_lines {
+            self.status = ModeStatus::Finished;
+        } else if game_state.is_game_over() {
+            self.status = ModeStatus::ToppedOut;
+        }
+        if self.status != ModeStatus::Playing {
+            self.finished = Some(self.started.elapsed());
+        }
+        self.status
+    }
+
+    pub fn start_level(&self) -> usize {
+        self.start_level
+    }
+    pub fn target_lines(&self) -> usize {
+        self.target_lines
+    }
+    pub fn elapsed(&self) -> Duration {
+        self.finished.unwrap_or_else(|| self.started.elapsed())
+    }
+    pub fn pieces_per_second(&self, game_state: &GameState) -> f64 {
+        let seconds = self.elapsed().as_secs_f64();
+        if seconds > 0.0 {
+            game_state.pieces_placed() as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+}
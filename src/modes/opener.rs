@@ -0,0 +1,121 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    Block, GARBAGE_COLOR, GameState, Position, TetraminoKind, diagram::Diagram, modes::ModeStatus,
+};
+
+// a placement that put cells where the target has none of its kind
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Deviation {
+    // 1 based, counting every piece locked in the drill
+    pub piece: usize,
+    pub kind: TetraminoKind,
+    pub cells: Vec<Position>,
+}
+
+// builds a target board piece by piece; garbage cells of the diagram are there from the start
+pub struct Opener {
+    name: String,
+    diagram: Diagram,
+    // cells still to be built and the piece meant to fill each
+    target: HashMap<Position, TetraminoKind>,
+    target_cells: usize,
+    deviations: Vec<Deviation>,
+    pieces_seen: usize,
+    status: ModeStatus,
+}
+
+impl Opener {
+    pub fn new(name: impl Into<String>, diagram: Diagram) -> Opener {
+        Opener {
+            name: name.into(),
+            diagram,
+            target: HashMap::new(),
+            target_cells: 0,
+            deviations: Vec::new(),
+            pieces_seen: 0,
+            status: ModeStatus::Playing,
+        }
+    }
+
+    pub fn configure(&mut self, game_state: GameState) -> GameState {
+        let rows = game_state.playfield_size().rows;
+        let game_state = if self.diagram.queue.is_empty() {
+            game_state
+        } else {
+            game_state.with_queue(&self.diagram.queue)
+        };
+        let mut game_state = game_state.with_hold(self.diagram.hold);
+        let garbage: HashSet<Block> = self
+            .diagram
+            .cells
+            .iter()
+            .filter(|cell| cell.kind.is_none())
+            .map(|cell| Block {
+                color: GARBAGE_COLOR,
+                coordinates: cell.position(rows),
+            })
+            .collect();
+        game_state.place_blocks(&garbage);
+        self.target = self
+            .diagram
+            .cells
+            .iter()
+            .filter_map(|cell| Some((cell.position(rows), cell.kind?)))
+            .collect();
+        self.target_cells = self.target.len();
+        game_state
+    }
+
+    pub fn update(&mut self, game_state: &GameState) -> ModeStatus {
+        if self.status != ModeStatus::Playing {
+            return self.status;
+        }
+        if game_state.pieces_placed() != self.pieces_seen {
+            self.pieces_seen = game_state.pieces_placed();
+            if let Some((kind, blocks)) = game_state.last_lock() {
+                let mut off_target: Vec<Position> = blocks
+                    .iter()
+                    .map(|block| block.coordinates)
+                    .filter(|cell| self.target.get(cell) != Some(&kind))
+                    .collect();
+                off_target.sort_by_key(|cell| (cell.row, cell.col));
+                for block in blocks {
+                    if self.target.get(&block.coordinates) == Some(&kind) {
+                        self.target.remove(&block.coordinates);
+                    }
+                }
+                if !off_target.is_empty() {
+                    self.deviations.push(Deviation {
+                        piece: self.pieces_seen,
+                        kind,
+                        cells: off_target,
+                    });
+                }
+            }
+        }
+        if self.target.is_empty() {
+            self.status = ModeStatus::Finished;
+        } else if game_state.is_game_over() {
+            self.status = ModeStatus::ToppedOut;
+        }
+        self.status
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn queue(&self) -> &[TetraminoKind] {
+        &self.diagram.queue
+    }
+    // cells of the target that are still empty, with the piece meant for them
+    pub fn remaining(&self) -> impl Iterator<Item = (&Position, &TetraminoKind)> {
+        self.target.iter()
+    }
+    pub fn progress(&self) -> (usize, usize) {
+        (self.target_cells - self.target.len(), self.target_cells)
+    }
+    pub fn deviations(&self) -> &[Deviation] {
+        &self.deviations
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::VecDeque;
+
+use crate::{GameState, modes::ModeStatus};
+
+pub const DEFAULT_UNDO_LIMIT: usize = 100;
+
+// free play where placements can be taken back; the game is snapshotted every time a new piece
+// comes up, so undo returns to the moment the previous piece spawned
+pub struct Practice {
+    limit: usize,
+    // snapshots from oldest to newest, the last one is the spawn of the piece in play
+    history: VecDeque<GameState>,
+    redo: Vec<GameState>,
+    pieces_seen: usize,
+}
+
+impl Default for Practice {
+    fn default() -> Self {
+        Practice::new(DEFAULT_UNDO_LIMIT)
+    }
+}
+
+impl Practice {
+    pub fn new(limit: usize) -> Practice {
+        Practice {
+            limit: limit.max(1),
+            history: VecDeque::new(),
+            redo: Vec::new(),
+            pieces_seen: 0,
+        }
+    }
+
+    pub fn update(&mut self, game_state: &GameState) -> ModeStatus {
+        if self.history.is_empty() || game_state.pieces_placed() != self.pieces_seen {
+            self.history.push_back(game_state.clone());
+            if self.history.len() > self.limit + 1 {
+                self.history.pop_front();
+            }
+            self.redo.clear();
+            self.pieces_seen = game_state.pieces_placed();
+        }
+        if game_state.is_game_over() {
+            ModeStatus::ToppedOut
+        } else {
+            ModeStatus::Playing
+        }
+    }
+
+    fn restore_current(&mut self, game_state: &mut GameState) {
+        if let Some(snapshot) = self.history.back() {
+            *game_state = snapshot.clone();
+            game_state.restart_timers();
+            self.pieces_seen = game_state.pieces_placed();
+        }
+    }
+
+    // takes back the last placement, false when there is nothing left to undo
+    pub fn undo(&mut self, game_state: &mut GameState) -> bool {
+        if self.history.len() < 2 {
+            return false;
+        }
+        self.redo.extend(self.history.pop_back());
+        self.restore_current(game_state);
+        true
+    }
+
+    pub fn redo(&mut self, game_state: &mut GameState) -> bool {
+        let Some(next) = self.redo.pop() else {
+            return false;
+        };
+        self.history.push_back(next);
+        self.restore_current(game_state);
+        true
+    }
+
+    pub fn undo_available(&self) -> usize {
+        self.history.len().saturating_sub(1)
+    }
+    pub fn redo_available(&self) -> usize {
+        self.redo.len()
+    }
+}
@@ -0,0 +1,83 @@
+use std::time::{Duration, Instant};
+
+use crate::{GameState, modes::ModeStatus};
+
+pub const DEFAULT_SPRINT_LINES: usize = 40;
+const SPLIT_LINES: usize = 10;
+
+pub struct Sprint {
+    target_lines: usize,
+    started: Instant,
+    splits: Vec<Duration>,
+    finished: Option<Duration>,
+    personal_best: Option<Duration>,
+    new_best: bool,
+    status: ModeStatus,
+}
+
+impl Default for Sprint {
+    fn default() -> Self {
+        Sprint::new(DEFAULT_SPRINT_LINES)
+    }
+}
+
+impl Sprint {
+    pub fn new(target_lines: usize) -> Sprint {
+        Sprint {
+            target_lines,
+            started: Instant::now(),
+            splits: Vec::new(),
+            finished: None,
+            personal_best: None,
+            new_best: false,
+            status: ModeStatus::Playing,
+        }
+    }
+
+    // the time to beat, usually the best on the records for target_lines
+    pub fn with_personal_best(mut self, personal_best: Option<Duration>) -> Sprint {
+        self.personal_best = personal_best;
+        self
+    }
+
+    pub fn update(&mut self, game_state: &GameState) -> ModeStatus {
+        if self.status != ModeStatus::Playing {
+            return self.status;
+        }
+        let elapsed = self.started.elapsed();
+        let lines = game_state.lines_cleared().min(self.target_lines);
+        while self.splits.len() < lines / SPLIT_LINES {
+            self.splits.push(elapsed);
+        }
+
+        if lines >= self.target_lines {
+            self.finished = Some(elapsed);
+            if self.personal_best.is_none_or(|best| elapsed < best) {
+                self.personal_best = Some(elapsed);
+                self.new_best = true;
+            }
+            self.status = ModeStatus::Finished;
+        } else if game_state.is_game_over() {
+            self.finished = Some(elapsed);
+            self.status = ModeStatus::ToppedOut;
+        }
+        self.status
+    }
+
+    pub fn target_lines(&self) -> usize {
+        self.target_lines
+    }
+    pub fn elapsed(&self) -> Duration {
+        self.finished.unwrap_or_else(|| self.started.elapsed())
+    }
+    // time at every 10 lines cleared
+    pub fn splits(&self) -> &[Duration] {
+        &self.splits
+    }
+    pub fn personal_best(&self) -> Option<Duration> {
+        self.personal_best
+    }
+    pub fn is_new_best(&self) -> bool {
+        self.new_best
+    }
+}
@@ -0,0 +1,52 @@
+use std::time::{Duration, Instant};
+
+use crate::{GameState, modes::ModeStatus};
+
+pub const ULTRA_DURATION: Duration = Duration::from_secs(3 * 60);
+pub const BLITZ_DURATION: Duration = Duration::from_secs(2 * 60);
+pub const BLITZ_LINES_PER_LEVEL: usize = 3;
+
+// score as much as possible before the clock runs out; shared by Ultra and Blitz
+pub struct TimeAttack {
+    duration: Duration,
+    started: Instant,
+    finished: Option<Duration>,
+    status: ModeStatus,
+}
+
+impl TimeAttack {
+    pub fn new(duration: Duration) -> TimeAttack {
+        TimeAttack {
+            duration,
+            started: Instant::now(),
+            finished: None,
+            status: ModeStatus::Playing,
+        }
+    }
+
+    pub fn update(&mut self, game_state: &GameState) -> ModeStatus {
+        if self.status != ModeStatus::Playing {
+            return self.status;
+        }
+        let elapsed = self.started.elapsed();
+        if elapsed >= self.duration {
+            self.finished = Some(self.duration);
+            self.status = ModeStatus::Finished;
+        } else if game_state.is_game_over() {
+            self.finished = Some(elapsed);
+            self.status = ModeStatus::ToppedOut;
+        }
+        self.status
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+    pub fn elapsed(&self) -> Duration {
+        self.finished
+            .unwrap_or_else(|| self.started.elapsed().min(self.duration))
+    }
+    pub fn remaining(&self) -> Duration {
+        self.duration - self.elapsed()
+    }
+}
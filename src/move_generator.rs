@@ -0,0 +1,318 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::{
+    Block, Playfield, PlayfieldSize, Position, RotationDirection, RotationState, TetraminoKind,
+    ruleset::RotationSystem, tetramino_shape::Tetramino,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter)]
+pub enum Input {
+    Left,
+    Right,
+    RotateClockwise,
+    RotateCounterClockwise,
+    // soft drop is held until the piece lands, so one input moves it down as far as it goes
+    SoftDrop,
+}
+
+#[derive(Clone, Debug)]
+pub struct Placement {
+    pub kind: TetraminoKind,
+    pub rotation_state: RotationState,
+    pub offset: Position,
+    pub blocks: HashSet<Block>,
+    pub inputs: Vec<Input>,
+}
+
+impl Placement {
+    // a hard drop does the work of a final soft drop, so it can be left out when locking
+    pub fn inputs_before_hard_drop(&self) -> &[Input] {
+        match self.inputs.split_last() {
+            Some((Input::SoftDrop, rest)) => rest,
+            _ => &self.inputs,
+        }
+    }
+    pub fn is_spin(&self) -> bool {
+        matches!(
+            self.inputs.last(),
+            Some(Input::RotateClockwise | Input::RotateCounterClockwise)
+        )
+    }
+}
+
+// occupancy lookup of a playfield, out of bound cells count as occupied
+#[derive(Clone)]
+pub struct CollisionMap {
+    size: PlayfieldSize,
+    occupied: Vec<bool>,
+}
+
+impl CollisionMap {
+    pub fn new(playfield: &Playfield) -> CollisionMap {
+        let size = playfield.size();
+        let mut occupied = vec![false; (size.rows * size.cols) as usize];
+        for block in playfield.placed_blocks().get_blocks() {
+            let coords = block.coordinates;
+            if coords.is_inbound(size.rows, size.cols) {
+                occupied[(coords.row * size.cols + coords.col) as usize] = true;
+            }
+        }
+        CollisionMap { size, occupied }
+    }
+
+    pub fn size(&self) -> PlayfieldSize {
+        self.size
+    }
+
+    pub fn is_occupied(&self, position: Position) -> bool {
+        !position.is_inbound(self.size.rows, self.size.cols)
+            || self.occupied[(position.row * self.size.cols + position.col) as usize]
+    }
+
+    pub fn fits(&self, cells: &[Position], offset: Position) -> bool {
+        cells.iter().all(|cell| !self.is_occupied(*cell + offset))
+    }
+
+    // locks the cells into the map and clears full rows, returning how many were cleared
+    pub fn place(&mut self, cells: &[Position], offset: Position) -> usize {
+        for cell in cells {
+            let coords = *cell + offset;
+            if coords.is_inbound(self.size.rows, self.size.cols) {
+                self.occupied[(coords.row * self.size.cols + coords.col) as usize] = true;
+            }
+        }
+        let cols = self.size.cols as usize;
+        let remaining: Vec<bool> = self
+            .occupied
+            .chunks(cols)
+            .filter(|row| !row.iter().all(|cell| *cell))
+            .flatten()
+            .copied()
+            .collect();
+        let cleared = self.occupied.len() - remaining.len();
+        self.occupied = vec![false; cleared];
+        self.occupied.extend(remaining);
+        cleared / cols
+    }
+
+    // height of every column measured from the floor, 0 for an empty column
+    pub fn column_heights(&self) -> Vec<isize> {
+        (0..self.size.cols)
+            .map(|col| {
+                (0..self.size.rows)
+                    .find(|row| self.is_occupied(Position::new(*row, col)))
+                    .map_or(0, |row| self.size.rows - row)
+            })
+            .collect()
+    }
+}
+
+struct RotationEntry {
+    state: RotationState,
+    blocks: HashSet<Block>,
+    cells: Vec<Position>,
+    clockwise_kicks: [Position; 5],
+    counter_clockwise_kicks: [Position; 5],
+}
+
+// all four rotations of a tetramino with their SRS kicks, indexed in clockwise order from spawn
+pub struct ShapeTable {
+    kind: TetraminoKind,
+    rotations: Vec<RotationEntry>,
+    // how many of the kicks a rotation may try
+    kick_tests: usize,
+}
+
+impl ShapeTable {
+    pub fn new(kind: TetraminoKind) -> ShapeTable {
+        let mut tetramino = Tetramino::construct(kind);
+        let mut rotations = Vec::with_capacity(4);
+        for _ in 0..4 {
+            let clockwise = tetramino.get_rotated_and_offsets(RotationDirection::Clockwise);
+            let counter_clockwise =
+                tetramino.get_rotated_and_offsets(RotationDirection::CounterClockwise);
+            rotations.push(RotationEntry {
+                state: tetramino.rotation_state(),
+                blocks: tetramino.get_blocks().clone(),
+                cells: tetramino
+                    .get_blocks()
+                    .iter()
+                    .map(|b| b.coordinates)
+                    .collect(),
+                clockwise_kicks: clockwise.kick_offsets,
+                counter_clockwise_kicks: counter_clockwise.kick_offsets,
+            });
+            tetramino = clockwise.tetramino;
+        }
+        ShapeTable {
+            kind,
+            rotations,
+            kick_tests: RotationSystem::Srs.kick_tests(),
+        }
+    }
+    pub fn with_rotation_system(mut self, rotation: RotationSystem) -> ShapeTable {
+        self.kick_tests = rotation.kick_tests();
+        self
+    }
+
+    pub fn kind(&self) -> TetraminoKind {
+        self.kind
+    }
+
+    pub fn cells(&self, rotation_state: RotationState) -> &[Position] {
+        &self.rotations[rotation_index(rotation_state)].cells
+    }
+
+    pub fn blocks_with_offset(
+        &self,
+        rotation_state: RotationState,
+        offset: Position,
+    ) -> HashSet<Block> {
+        self.rotations[rotation_index(rotation_state)]
+            .blocks
+            .iter()
+            .map(|b| Block {
+                color: b.color,
+                coordinates: b.coordinates + offset,
+            })
+            .collect()
+    }
+
+    // mirrors the movement of GameState: shifts by one column, SRS rotation trying kicks in order
+    pub fn apply(
+        &self,
+        collision_map: &CollisionMap,
+        rotation_state: RotationState,
+        offset: Position,
+        input: Input,
+    ) -> Option<(RotationState, Position)> {
+        let index = rotation_index(rotation_state);
+        let cells = &self.rotations[index].cells;
+        match input {
+            Input::Left | Input::Right => {
+                let shifted = offset
+                    + match input {
+                        Input::Left => Position::new(0, -1),
+                        _ => Position::new(0, 1),
+                    };
+                collision_map
+                    .fits(cells, shifted)
+                    .then_some((rotation_state, shifted))
+            }
+            Input::RotateClockwise | Input::RotateCounterClockwise => {
+                let (target, kicks) = match input {
+                    Input::RotateClockwise => {
+                        ((index + 1) % 4, &self.rotations[index].clockwise_kicks)
+                    }
+                    _ => (
+                        (index + 3) % 4,
+                        &self.rotations[index].counter_clockwise_kicks,
+                    ),
+                };
+                let target = &self.rotations[target];
+                kicks
+                    .iter()
+                    .take(self.kick_tests)
+                    .map(|kick| offset + *kick)
+                    .find(|kicked| collision_map.fits(&target.cells, *kicked))
+                    .map(|kicked| (target.state, kicked))
+            }
+            Input::SoftDrop => {
+                let dropped = drop_offset(collision_map, cells, offset);
+                (dropped != offset).then_some((rotation_state, dropped))
+            }
+        }
+    }
+}
+
+pub fn drop_offset(collision_map: &CollisionMap, cells: &[Position], offset: Position) -> Position {
+    let mut dropped = offset;
+    while collision_map.fits(cells, dropped + Position::new(1, 0)) {
+        dropped += Position::new(1, 0);
+    }
+    dropped
+}
+
+fn rotation_index(rotation_state: RotationState) -> usize {
+    match rotation_state {
+        RotationState::Init => 0,
+        RotationState::Right => 1,
+        RotationState::Flip => 2,
+        RotationState::Left => 3,
+    }
+}
+
+pub fn generate_placements(
+    playfield: &Playfield,
+    kind: TetraminoKind,
+    rotation: RotationSystem,
+) -> Vec<Placement> {
+    generate_placements_from(
+        &CollisionMap::new(playfield),
+        &ShapeTable::new(kind).with_rotation_system(rotation),
+        RotationState::Init,
+        playfield.spawn_offset(),
+    )
+}
+
+// breadth-first search over (rotation, offset) states, so the first path found to every
+// resting position is the shortest one; positions covering the same cells are reported once
+pub fn generate_placements_from(
+    collision_map: &CollisionMap,
+    shapes: &ShapeTable,
+    rotation_state: RotationState,
+    offset: Position,
+) -> Vec<Placement> {
+    let start = (rotation_state, offset);
+    if !collision_map.fits(shapes.cells(start.0), start.1) {
+        return Vec::new();
+    }
+
+    let mut parents: HashMap<(RotationState, Position), ((RotationState, Position), Input)> =
+        HashMap::new();
+    let mut visited = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    let mut seen_cells = HashSet::new();
+    let mut placements = Vec::new();
+
+    while let Some(node) = queue.pop_front() {
+        let (rotation_state, offset) = node;
+        let cells = shapes.cells(rotation_state);
+        if !collision_map.fits(cells, offset + Position::new(1, 0)) {
+            let mut key: Vec<(isize, isize)> = cells
+                .iter()
+                .map(|cell| (cell.row + offset.row, cell.col + offset.col))
+                .collect();
+            key.sort();
+            if seen_cells.insert(key) {
+                let mut inputs = Vec::new();
+                let mut current = node;
+                while let Some((parent, input)) = parents.get(&current) {
+                    inputs.push(*input);
+                    current = *parent;
+                }
+                inputs.reverse();
+                placements.push(Placement {
+                    kind: shapes.kind(),
+                    rotation_state,
+                    offset,
+                    blocks: shapes.blocks_with_offset(rotation_state, offset),
+                    inputs,
+                });
+            }
+        }
+
+        for input in Input::iter() {
+            if let Some(next) = shapes.apply(collision_map, rotation_state, offset, input)
+                && visited.insert(next)
+            {
+                parents.insert(next, (node, input));
+                queue.push_back(next);
+            }
+        }
+    }
+    placements
+}
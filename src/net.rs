@@ -0,0 +1,324 @@
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{GameState, LockRecord, events, ruleset::Ruleset};
+
+pub const PROTOCOL_VERSION: u32 = 5;
+
+// the seed and the ruleset are everything both sides need to build identical games
+fn new_game(ruleset: &Ruleset, seed: u64) -> GameState {
+    ruleset.new_game().with_seed(seed).with_events()
+}
+
+// one JSON object per line
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    Hello {
+        version: u32,
+    },
+    Start {
+        seed: u64,
+        ruleset: Ruleset,
+    },
+    // the engine only depends on the seed and the placements, so a lock is all the peer needs
+    // to rebuild the board; the hash of the sender's game after it catches desyncs
+    Lock {
+        piece: usize,
+        record: LockRecord,
+        board_hash: u64,
+    },
+    GameOver,
+    Desync {
+        piece: usize,
+    },
+}
+
+#[derive(Debug)]
+pub enum NetError {
+    Io(io::Error),
+    Protocol(String),
+    VersionMismatch { local: u32, remote: u32 },
+    Disconnected,
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::Io(error) => write!(f, "connection error: {error}"),
+            NetError::Protocol(message) => write!(f, "protocol error: {message}"),
+            NetError::VersionMismatch { local, remote } => write!(
+                f,
+                "protocol version mismatch: ours is {local}, the peer runs {remote}"
+            ),
+            NetError::Disconnected => write!(f, "peer disconnected"),
+        }
+    }
+}
+
+impl std::error::Error for NetError {}
+
+impl From<io::Error> for NetError {
+    fn from(value: io::Error) -> Self {
+        NetError::Io(value)
+    }
+}
+
+// waits for a peer without blocking, for hosts that have frames to draw meanwhile
+pub struct Host {
+    listener: TcpListener,
+    seed: u64,
+    ruleset: Ruleset,
+}
+
+impl Host {
+    pub fn bind(
+        address: impl ToSocketAddrs,
+        seed: u64,
+        ruleset: Ruleset,
+    ) -> Result<Host, NetError> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Host {
+            listener,
+            seed,
+            ruleset,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // the connection once a peer came and agreed to the game, None while nobody has
+    pub fn poll(&self) -> Result<Option<(Connection, u64, Ruleset)>, NetError> {
+        let stream = match self.listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        // some platforms hand out accepted sockets non-blocking like their listener
+        stream.set_nonblocking(false)?;
+        let mut connection = Connection::new(stream)?;
+        connection.handshake()?;
+        connection.send(&Message::Start {
+            seed: self.seed,
+            ruleset: self.ruleset.clone(),
+        })?;
+        connection.stream.set_nonblocking(true)?;
+        Ok(Some((connection, self.seed, self.ruleset.clone())))
+    }
+}
+
+pub struct Connection {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+    // a line still being received when the socket ran dry
+    partial: String,
+    // what the socket didn't take yet, flushed before anything new goes out
+    unsent: Vec<u8>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> Result<Connection, NetError> {
+        stream.set_nodelay(true)?;
+        Ok(Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            stream,
+            partial: String::new(),
+            unsent: Vec::new(),
+        })
+    }
+
+    pub fn connect(address: impl ToSocketAddrs) -> Result<(Connection, u64, Ruleset), NetError> {
+        let mut connection = Connection::new(TcpStream::connect(address)?)?;
+        connection.handshake()?;
+        match connection.receive_blocking()? {
+            Message::Start { seed, ruleset } => {
+                ruleset
+                    .validate()
+                    .map_err(|error| NetError::Protocol(format!("unplayable rules: {error}")))?;
+                connection.stream.set_nonblocking(true)?;
+                Ok((connection, seed, ruleset))
+            }
+            other => Err(NetError::Protocol(format!("expected start, got {other:?}"))),
+        }
+    }
+
+    fn handshake(&mut self) -> Result<(), NetError> {
+        self.send(&Message::Hello {
+            version: PROTOCOL_VERSION,
+        })?;
+        match self.receive_blocking()? {
+            Message::Hello { version } if version == PROTOCOL_VERSION => Ok(()),
+            Message::Hello { version } => Err(NetError::VersionMismatch {
+                local: PROTOCOL_VERSION,
+                remote: version,
+            }),
+            other => Err(NetError::Protocol(format!("expected hello, got {other:?}"))),
+        }
+    }
+
+    pub fn send(&mut self, message: &Message) -> Result<(), NetError> {
+        let mut line =
+            serde_json::to_string(message).map_err(|e| NetError::Protocol(e.to_string()))?;
+        line.push('\n');
+        self.unsent.extend_from_slice(line.as_bytes());
+        self.flush()
+    }
+
+    // writes what the socket takes; once the game runs the socket is non-blocking and a full
+    // send buffer leaves the rest for the next flush
+    pub fn flush(&mut self) -> Result<(), NetError> {
+        while !self.unsent.is_empty() {
+            match self.stream.write(&self.unsent) {
+                Ok(0) => return Err(NetError::Disconnected),
+                Ok(written) => {
+                    self.unsent.drain(..written);
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(error) => return Err(error.into()),
+            }
+        }
+        Ok(())
+    }
+
+    fn receive_blocking(&mut self) -> Result<Message, NetError> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(NetError::Disconnected);
+        }
+        parse(&line)
+    }
+
+    // every complete message that arrived since the last poll, never blocks
+    pub fn poll(&mut self) -> Result<Vec<Message>, NetError> {
+        let mut messages = Vec::new();
+        loop {
+            match self.reader.read_line(&mut self.partial) {
+                Ok(0) => return Err(NetError::Disconnected),
+                Ok(_) if self.partial.ends_with('\n') => {
+                    messages.push(parse(&self.partial)?);
+                    self.partial.clear();
+                }
+                Ok(_) => {}
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(messages),
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+}
+
+fn parse(line: &str) -> Result<Message, NetError> {
+    serde_json::from_str(line.trim_end()).map_err(|e| NetError::Protocol(e.to_string()))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetStatus {
+    Playing,
+    Won,
+    Lost,
+    // piece number after which the boards stopped matching
+    Desynced { piece: usize },
+}
+
+// our game, played locally, and a copy of the peer's game rebuilt from its locks
+pub struct NetVersus {
+    connection: Connection,
+    local: GameState,
+    remote: GameState,
+    status: NetStatus,
+    game_over_sent: bool,
+}
+
+impl NetVersus {
+    pub fn new(connection: Connection, seed: u64, ruleset: &Ruleset) -> NetVersus {
+        NetVersus {
+            connection,
+            local: new_game(ruleset, seed),
+            remote: new_game(ruleset, seed),
+            status: NetStatus::Playing,
+            game_over_sent: false,
+        }
+    }
+
+    pub fn local(&self) -> &GameState {
+        &self.local
+    }
+    pub fn local_mut(&mut self) -> &mut GameState {
+        &mut self.local
+    }
+    pub fn remote(&self) -> &GameState {
+        &self.remote
+    }
+    pub fn status(&self) -> NetStatus {
+        self.status
+    }
+
+    // call once per frame after the local game has processed its input
+    pub fn update(&mut self) -> Result<NetStatus, NetError> {
+        if self.status != NetStatus::Playing {
+            // what is left, like our game over, still goes out; the peer may be gone by now
+            self.connection.flush().ok();
+            return Ok(self.status);
+        }
+        self.connection.flush()?;
+        let records: Vec<LockRecord> = events::lock_records(&self.local.take_events()).collect();
+        let last = records.len().saturating_sub(1);
+        for (i, record) in records.into_iter().enumerate() {
+            let piece = self.local.pieces_placed() - last + i;
+            let board_hash = if i == last {
+                self.local.board_hash()
+            } else {
+                0
+            };
+            self.connection.send(&Message::Lock {
+                piece,
+                record,
+                board_hash,
+            })?;
+        }
+        // what was sent travels inside the lock records, the counter only has to be drained
+        self.local.take_outgoing_garbage();
+
+        if self.local.is_game_over() && !self.game_over_sent {
+            self.connection.send(&Message::GameOver)?;
+            self.game_over_sent = true;
+            self.status = NetStatus::Lost;
+        }
+
+        for message in self.connection.poll()? {
+            match message {
+                Message::Lock {
+                    piece,
+                    record,
+                    board_hash,
+                } => {
+                    self.remote.replay_lock(&record);
+                    self.remote.take_events();
+                    self.local.receive_garbage(record.attack);
+                    if board_hash != 0 && board_hash != self.remote.board_hash() {
+                        self.connection.send(&Message::Desync { piece })?;
+                        self.status = NetStatus::Desynced { piece };
+                    }
+                }
+                Message::GameOver if self.status == NetStatus::Playing => {
+                    self.status = NetStatus::Won;
+                }
+                Message::Desync { piece } => self.status = NetStatus::Desynced { piece },
+                Message::GameOver => {}
+                other => {
+                    return Err(NetError::Protocol(format!(
+                        "unexpected message during play: {other:?}"
+                    )));
+                }
+            }
+        }
+        Ok(self.status)
+    }
+}
@@ -0,0 +1,402 @@
+use std::collections::HashSet;
+
+use crate::{
+    Playfield, Position, RotationState, TetraminoKind,
+    diagram::{Diagram, DiagramCell},
+    move_generator::{CollisionMap, Input, ShapeTable, generate_placements_from},
+    ruleset::RotationSystem,
+};
+
+pub const DEFAULT_MAX_HEIGHT: isize = 4;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolvedPiece {
+    pub kind: TetraminoKind,
+    // hold is pressed before this piece is played
+    pub hold: bool,
+    pub rotation_state: RotationState,
+    // where the piece goes on the board as it is when the piece comes up
+    pub offset: Position,
+    pub inputs: Vec<Input>,
+    // the cells on the board the search started from, rows cleared on the way don't move them
+    pub cells: Vec<Position>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Solution {
+    pub height: isize,
+    pub pieces: Vec<SolvedPiece>,
+}
+
+impl Solution {
+    // the starting board with every piece of the solution drawn in
+    pub fn to_diagram(&self, playfield: &Playfield) -> Diagram {
+        let rows = playfield.size().rows;
+        let garbage = playfield
+            .placed_blocks()
+            .get_blocks()
+            .iter()
+            .map(|block| DiagramCell {
+                x: block.coordinates.col,
+                y: rows - 1 - block.coordinates.row,
+                kind: None,
+            });
+        let pieces = self.pieces.iter().flat_map(|piece| {
+            piece.cells.iter().map(|cell| DiagramCell {
+                x: cell.col,
+                y: rows - 1 - cell.row,
+                kind: Some(piece.kind),
+            })
+        });
+        Diagram {
+            cells: garbage.chain(pieces).collect(),
+            queue: self.pieces.iter().map(|piece| piece.kind).collect(),
+            hold: None,
+        }
+    }
+
+    pub fn to_fumen(&self, playfield: &Playfield) -> String {
+        self.to_diagram(playfield).to_fumen()
+    }
+}
+
+#[derive(Clone)]
+struct Search<'a> {
+    map: CollisionMap,
+    // rows still to clear, counted from the bottom
+    height: isize,
+    // the row each current board row was on at the start, bottom first
+    origin_rows: Vec<isize>,
+    current: Option<TetraminoKind>,
+    queue: &'a [TetraminoKind],
+    hold: Option<TetraminoKind>,
+}
+
+// the region still to fill, pieces still in the queue, the current piece and the held one
+type SearchKey = (
+    Vec<bool>,
+    usize,
+    Option<TetraminoKind>,
+    Option<TetraminoKind>,
+);
+
+struct Solver {
+    spawn_offset: Position,
+    rotation: RotationSystem,
+    max_solutions: usize,
+    solutions: Vec<Vec<SolvedPiece>>,
+    // positions already shown to lead nowhere
+    dead_ends: HashSet<SearchKey>,
+    // finished boards, orders of play that build the same one are reported once
+    layouts: HashSet<Vec<(isize, isize, u8)>>,
+}
+
+// placement sequences that clear the board completely using no more than `max_height` rows;
+// every placement is reachable from spawn under `rotation`, the first piece played is `current`
+// or a held one
+pub fn solve(
+    playfield: &Playfield,
+    rotation: RotationSystem,
+    current: TetraminoKind,
+    queue: &[TetraminoKind],
+    hold: Option<TetraminoKind>,
+    max_height: isize,
+    max_solutions: usize,
+) -> Vec<Solution> {
+    let map = CollisionMap::new(playfield);
+    let size = map.size();
+    let filled = playfield.placed_blocks().get_blocks().len() as isize;
+    let stack_height = playfield
+        .placed_blocks()
+        .get_blocks()
+        .iter()
+        .map(|block| size.rows - block.coordinates.row)
+        .max()
+        .unwrap_or(0);
+    let pieces = 1 + queue.len() as isize + hold.is_some() as isize;
+
+    let mut solutions = Vec::new();
+    for height in stack_height.max(1)..=max_height.min(size.rows) {
+        let empty = height * size.cols - filled;
+        if empty % 4 != 0 || empty / 4 > pieces {
+            continue;
+        }
+        let mut solver = Solver {
+            spawn_offset: playfield.spawn_offset(),
+            rotation,
+            max_solutions: max_solutions - solutions.len(),
+            solutions: Vec::new(),
+            dead_ends: HashSet::new(),
+            layouts: HashSet::new(),
+        };
+        let search = Search {
+            map: map.clone(),
+            height,
+            origin_rows: (0..size.rows).map(|row| size.rows - 1 - row).collect(),
+            current: Some(current),
+            queue,
+            hold,
+        };
+        solver.search(search, &mut Vec::new());
+        solutions.extend(
+            solver
+                .solutions
+                .into_iter()
+                .map(|pieces| Solution { height, pieces }),
+        );
+        if solutions.len() >= max_solutions {
+            break;
+        }
+    }
+    solutions
+}
+
+// the next piece in a queue and the ones after it
+fn advance(queue: &[TetraminoKind]) -> (Option<TetraminoKind>, &[TetraminoKind]) {
+    (queue.first().copied(), queue.get(1..).unwrap_or(&[]))
+}
+
+impl<'a> Search<'a> {
+    // the region that has to be filled, row major from the top of it
+    fn region(&self) -> Vec<bool> {
+        let size = self.map.size();
+        (size.rows - self.height..size.rows)
+            .flat_map(|row| (0..size.cols).map(move |col| Position::new(row, col)))
+            .map(|position| self.map.is_occupied(position))
+            .collect()
+    }
+
+    // every pocket of empty cells has to take a whole number of pieces
+    fn pockets_fit(&self) -> bool {
+        let cols = self.map.size().cols;
+        let mut region = self.region();
+        for start in 0..region.len() {
+            if region[start] {
+                continue;
+            }
+            let mut size = 0;
+            let mut stack = vec![start];
+            region[start] = true;
+            while let Some(index) = stack.pop() {
+                size += 1;
+                let (row, col) = (index as isize / cols, index as isize % cols);
+                for (row, col) in [
+                    (row - 1, col),
+                    (row + 1, col),
+                    (row, col - 1),
+                    (row, col + 1),
+                ] {
+                    let neighbour = (row * cols + col) as usize;
+                    if (0..self.height).contains(&row)
+                        && (0..cols).contains(&col)
+                        && !region[neighbour]
+                    {
+                        region[neighbour] = true;
+                        stack.push(neighbour);
+                    }
+                }
+            }
+            if size % 4 != 0 {
+                return false;
+            }
+        }
+        true
+    }
+
+    // the pieces that can be played next: the current one, or the held one / the one after
+    // it through hold, and what is left to play afterwards
+    fn choices(&self) -> Vec<(TetraminoKind, bool, Search<'a>)> {
+        let mut choices = Vec::new();
+        if let Some(current) = self.current {
+            let (next, rest) = advance(self.queue);
+            choices.push((
+                current,
+                false,
+                Search {
+                    current: next,
+                    queue: rest,
+                    ..self.clone()
+                },
+            ));
+        }
+        match (self.hold, self.current) {
+            (Some(held), current) if Some(held) != current => {
+                let (next, rest) = advance(self.queue);
+                choices.push((
+                    held,
+                    true,
+                    Search {
+                        current: next,
+                        queue: rest,
+                        hold: current,
+                        ..self.clone()
+                    },
+                ));
+            }
+            (None, Some(current)) => {
+                if let Some((played, rest)) = self.queue.split_first() {
+                    let (next, rest) = advance(rest);
+                    choices.push((
+                        *played,
+                        true,
+                        Search {
+                            current: next,
+                            queue: rest,
+                            hold: Some(current),
+                            ..self.clone()
+                        },
+                    ));
+                }
+            }
+            _ => {}
+        }
+        choices
+    }
+}
+
+impl Solver {
+    fn search(&mut self, search: Search, played: &mut Vec<SolvedPiece>) {
+        if self.solutions.len() >= self.max_solutions {
+            return;
+        }
+        if search.height == 0 {
+            let mut layout: Vec<(isize, isize, u8)> = played
+                .iter()
+                .flat_map(|piece| {
+                    piece
+                        .cells
+                        .iter()
+                        .map(|cell| (cell.row, cell.col, piece.kind as u8))
+                })
+                .collect();
+            layout.sort();
+            if self.layouts.insert(layout) {
+                self.solutions.push(played.clone());
+            }
+            return;
+        }
+        let key = (
+            search.region(),
+            search.queue.len(),
+            search.current,
+            search.hold,
+        );
+        if self.dead_ends.contains(&key) {
+            return;
+        }
+        let found = self.solutions.len();
+
+        let size = search.map.size();
+        let floor = size.rows - search.height;
+        for (kind, hold, next) in search.choices() {
+            let shapes = ShapeTable::new(kind).with_rotation_system(self.rotation);
+            for placement in generate_placements_from(
+                &search.map,
+                &shapes,
+                RotationState::Init,
+                self.spawn_offset,
+            ) {
+                let cells: Vec<Position> = placement.blocks.iter().map(|b| b.coordinates).collect();
+                if cells.iter().any(|cell| cell.row < floor) {
+                    continue;
+                }
+                let mut map = search.map.clone();
+                let full_rows: Vec<isize> = (floor..size.rows)
+                    .filter(|row| {
+                        (0..size.cols).all(|col| {
+                            let position = Position::new(*row, col);
+                            map.is_occupied(position) || cells.contains(&position)
+                        })
+                    })
+                    .collect();
+                let lines = map.place(shapes.cells(placement.rotation_state), placement.offset);
+                debug_assert_eq!(lines, full_rows.len());
+
+                // rows are kept bottom first, a cleared row takes its original number with it
+                let origin_rows = |row: isize| search.origin_rows[(size.rows - 1 - row) as usize];
+                let original_cells = cells
+                    .iter()
+                    .map(|cell| Position::new(origin_rows(cell.row), cell.col))
+                    .collect();
+                // full_rows go top down, so the highest index goes first and the ones after
+                // it still point at the right rows
+                let mut origin = search.origin_rows.clone();
+                for row in &full_rows {
+                    origin.remove((size.rows - 1 - row) as usize);
+                }
+
+                let child = Search {
+                    map,
+                    height: search.height - lines as isize,
+                    origin_rows: origin,
+                    ..next.clone()
+                };
+                if child.height > 0 && !child.pockets_fit() {
+                    continue;
+                }
+                played.push(SolvedPiece {
+                    kind,
+                    hold,
+                    rotation_state: placement.rotation_state,
+                    offset: placement.offset,
+                    inputs: placement.inputs,
+                    cells: original_cells,
+                });
+                self.search(child, played);
+                played.pop();
+                if self.solutions.len() >= self.max_solutions {
+                    return;
+                }
+            }
+        }
+        if self.solutions.len() == found {
+            self.dead_ends.insert(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Block, GARBAGE_COLOR, PlayfieldSize};
+
+    // the first O clears two rows at once, the pieces after it must still land on the rows
+    // above them in the starting board
+    #[test]
+    fn double_clear_keeps_later_cells_in_place() {
+        let mut playfield = Playfield::new(PlayfieldSize { rows: 20, cols: 10 });
+        let garbage: HashSet<Block> = [(16, 6), (17, 6), (18, 8), (19, 8)]
+            .into_iter()
+            .flat_map(|(row, width)| {
+                (0..width).map(move |col| Block {
+                    color: GARBAGE_COLOR,
+                    coordinates: Position::new(row, col),
+                })
+            })
+            .collect();
+        playfield.put_blocks(&garbage);
+        let queue = [TetraminoKind::O, TetraminoKind::O];
+        let solutions = solve(
+            &playfield,
+            RotationSystem::Srs,
+            TetraminoKind::O,
+            &queue,
+            None,
+            4,
+            10,
+        );
+        assert!(!solutions.is_empty());
+        let full: HashSet<Position> = (16..20)
+            .flat_map(|row| (0..10).map(move |col| Position::new(row, col)))
+            .collect();
+        for solution in solutions {
+            let cells: Vec<Position> = garbage
+                .iter()
+                .map(|block| block.coordinates)
+                .chain(solution.pieces.iter().flat_map(|piece| piece.cells.clone()))
+                .collect();
+            assert_eq!(cells.len(), full.len());
+            assert_eq!(cells.into_iter().collect::<HashSet<_>>(), full);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+// the environment as a Python extension module, built with --features python
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    PlayfieldSize, TetraminoKind,
+    env::{Env, Observation, PREVIEW_SLOTS},
+};
+
+// goes to Python as a dict: the board as a list of rows, and the pieces with -1 for none,
+// also in the preview slots the ruleset doesn't show
+#[derive(IntoPyObject)]
+struct PyObservation {
+    board: Vec<Vec<u8>>,
+    current: i8,
+    preview: [i8; PREVIEW_SLOTS],
+    hold: i8,
+}
+
+fn kind_index(kind: Option<TetraminoKind>) -> i8 {
+    kind.map_or(-1, |kind| kind as i8)
+}
+
+fn to_python(observation: Observation) -> PyObservation {
+    PyObservation {
+        board: observation
+            .board
+            .chunks(observation.cols as usize)
+            .map(<[u8]>::to_vec)
+            .collect(),
+        current: kind_index(Some(observation.current)),
+        preview: observation.preview.map(kind_index),
+        hold: kind_index(observation.hold),
+    }
+}
+
+#[pyclass(name = "Env")]
+struct PyEnv {
+    env: Env,
+}
+
+#[pymethods]
+impl PyEnv {
+    #[new]
+    #[pyo3(signature = (seed = 0, rows = 20, cols = 10))]
+    // a size no game fits raises ValueError
+    fn new(seed: u64, rows: isize, cols: isize) -> PyResult<PyEnv> {
+        let env = Env::new(PlayfieldSize { rows, cols }, seed)
+            .map_err(|error| PyValueError::new_err(error.to_string()))?;
+        Ok(PyEnv { env })
+    }
+
+    fn reset(&mut self, seed: u64) -> PyObservation {
+        to_python(self.env.reset(seed))
+    }
+
+    // (observation, reward, done, invalid)
+    fn step(&mut self, action: usize) -> (PyObservation, f32, bool, bool) {
+        let step = self.env.step(action);
+        (
+            to_python(self.env.observe()),
+            step.reward,
+            step.done,
+            step.invalid,
+        )
+    }
+
+    fn observe(&self) -> PyObservation {
+        to_python(self.env.observe())
+    }
+
+    fn action_count(&self) -> usize {
+        self.env.action_count()
+    }
+
+    fn action_mask(&self) -> Vec<bool> {
+        self.env.action_mask()
+    }
+
+    fn clone(&self) -> PyEnv {
+        PyEnv {
+            env: self.env.clone(),
+        }
+    }
+}
+
+#[pymodule]
+fn tetrs(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyEnv>()
+}
@@ -0,0 +1,289 @@
+use std::{
+    collections::BTreeMap,
+    fmt, io,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{GameState, LockRecord, ruleset::Ruleset, scoring::SpinKind, storage};
+
+pub const RECORDS_FILE: &str = "records.json";
+// where a records file that doesn't parse is moved, so saving doesn't write over it
+pub const RECORDS_BACKUP_FILE: &str = "records.json.bak";
+// entries kept per leaderboard
+pub const LEADERBOARD_SIZE: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Leaderboard {
+    Marathon,
+    Ultra,
+    Sprint { lines: usize },
+}
+
+impl Leaderboard {
+    // the standard rules keep the plain key, every other ruleset gets boards of its own
+    fn key(self, ruleset_hash: u64) -> String {
+        let key = match self {
+            Leaderboard::Marathon => "marathon".to_string(),
+            Leaderboard::Ultra => "ultra".to_string(),
+            Leaderboard::Sprint { lines } => format!("sprint_{lines}"),
+        };
+        if ruleset_hash == Ruleset::default().fingerprint() {
+            key
+        } else {
+            format!("{key}_{ruleset_hash:016x}")
+        }
+    }
+
+    // whether `a` ranks above `b`: score for marathon and ultra, time for sprint
+    fn beats(self, a: &Record, b: &Record) -> bool {
+        match self {
+            Leaderboard::Sprint { .. } => a.time_ms < b.time_ms,
+            _ => a.score > b.score,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    pub score: usize,
+    pub lines: usize,
+    pub time_ms: u64,
+    // seconds since the unix epoch
+    pub date: u64,
+    // the fingerprint of the ruleset played, see Ruleset::fingerprint
+    pub ruleset_hash: u64,
+    // the replay file of the game, when one was saved
+    pub replay: Option<String>,
+}
+
+impl Record {
+    pub fn new(game_state: &GameState, time: Duration) -> Record {
+        Record {
+            score: game_state.score(),
+            lines: game_state.lines_cleared(),
+            time_ms: time.as_millis() as u64,
+            date: now(),
+            ruleset_hash: game_state.ruleset().fingerprint(),
+            replay: None,
+        }
+    }
+
+    pub fn with_replay(mut self, replay: Option<String>) -> Record {
+        self.replay = replay;
+        self
+    }
+
+    pub fn time(&self) -> Duration {
+        Duration::from_millis(self.time_ms)
+    }
+}
+
+// T-spin clears by the number of lines they cleared, zero to three
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TSpinCounts {
+    pub mini: [usize; 4],
+    pub full: [usize; 4],
+}
+
+// totals over every game played
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub games: usize,
+    pub pieces: usize,
+    pub lines: usize,
+    pub attack: usize,
+    pub play_time_ms: u64,
+    pub t_spins: TSpinCounts,
+}
+
+impl Stats {
+    pub fn add_game(&mut self, game_state: &GameState, time: Duration) {
+        self.games += 1;
+        self.pieces += game_state.pieces_placed();
+        self.lines += game_state.lines_cleared();
+        self.attack += game_state.attack_sent();
+        self.play_time_ms += time.as_millis() as u64;
+        let scoring = game_state.scoring();
+        for lines in 0..4 {
+            self.t_spins.mini[lines] += scoring.clear_count(SpinKind::Mini, lines);
+            self.t_spins.full[lines] += scoring.clear_count(SpinKind::Full, lines);
+        }
+    }
+
+    pub fn play_time(&self) -> Duration {
+        Duration::from_millis(self.play_time_ms)
+    }
+    // pieces per second over all play time
+    pub fn pieces_per_second(&self) -> f64 {
+        let seconds = self.play_time().as_secs_f64();
+        if seconds > 0.0 {
+            self.pieces as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+    // attack per minute over all play time
+    pub fn attack_per_minute(&self) -> f64 {
+        let minutes = self.play_time().as_secs_f64() / 60.0;
+        if minutes > 0.0 {
+            self.attack as f64 / minutes
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RecordsError {
+    // the file is there but couldn't be read, nothing may be saved over it
+    Io(io::Error),
+    // the file didn't parse; backup is how moving it to RECORDS_BACKUP_FILE went
+    Corrupt {
+        error: serde_json::Error,
+        backup: io::Result<()>,
+    },
+}
+
+impl RecordsError {
+    // whether the old file is out of the way, so saving new records loses nothing
+    pub fn allows_saving(&self) -> bool {
+        matches!(self, RecordsError::Corrupt { backup: Ok(()), .. })
+    }
+}
+
+impl fmt::Display for RecordsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordsError::Io(error) => write!(f, "can't read {RECORDS_FILE}: {error}"),
+            RecordsError::Corrupt { error, backup } => {
+                write!(f, "{RECORDS_FILE} is broken ({error}), ")?;
+                match backup {
+                    Ok(()) => write!(f, "it was moved to {RECORDS_BACKUP_FILE}"),
+                    Err(error) => write!(f, "and couldn't be moved aside: {error}"),
+                }
+            }
+        }
+    }
+}
+
+impl std::error::Error for RecordsError {}
+
+// the local leaderboards and lifetime stats, kept as one json file in the data dir
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Records {
+    leaderboards: BTreeMap<String, Vec<Record>>,
+    stats: Stats,
+}
+
+impl Records {
+    // no file yet is a fresh start; a broken one is moved to RECORDS_BACKUP_FILE
+    pub fn load() -> Result<Records, RecordsError> {
+        let text = match storage::read_data(RECORDS_FILE) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Records::default()),
+            Err(error) => return Err(RecordsError::Io(error)),
+        };
+        serde_json::from_str(&text).map_err(|error| RecordsError::Corrupt {
+            error,
+            backup: storage::rename_data(RECORDS_FILE, RECORDS_BACKUP_FILE),
+        })
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let text = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        storage::write_data(RECORDS_FILE, &text)
+    }
+
+    // best first, only games played with the ruleset of that fingerprint
+    pub fn leaderboard(&self, board: Leaderboard, ruleset_hash: u64) -> &[Record] {
+        self.leaderboards
+            .get(&board.key(ruleset_hash))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn best(&self, board: Leaderboard, ruleset_hash: u64) -> Option<&Record> {
+        self.leaderboard(board, ruleset_hash).first()
+    }
+
+    // the place the record would take on the board of its ruleset, None if it wouldn't make it
+    pub fn rank(&self, board: Leaderboard, record: &Record) -> Option<usize> {
+        let entries = self.leaderboard(board, record.ruleset_hash);
+        let rank = entries
+            .iter()
+            .position(|entry| board.beats(record, entry))
+            .unwrap_or(entries.len());
+        (rank < LEADERBOARD_SIZE).then_some(rank)
+    }
+
+    // puts the record in its place on the board, None if it didn't make it
+    pub fn submit(&mut self, board: Leaderboard, record: Record) -> Option<usize> {
+        let rank = self.rank(board, &record)?;
+        let entries = self
+            .leaderboards
+            .entry(board.key(record.ruleset_hash))
+            .or_default();
+        entries.insert(rank, record);
+        entries.truncate(LEADERBOARD_SIZE);
+        Some(rank)
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+    pub fn stats_mut(&mut self) -> &mut Stats {
+        &mut self.stats
+    }
+}
+
+// everything needed to play a game back: its seed, its rules and how every piece locked
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub ruleset: Ruleset,
+    pub locks: Vec<LockRecord>,
+}
+
+impl Replay {
+    // writes the replay next to the records, the file name is what a record links to
+    pub fn save(&self) -> std::io::Result<String> {
+        let file_name = format!("replay_{}_{}.json", now(), self.seed);
+        let text = serde_json::to_string(self).map_err(std::io::Error::other)?;
+        storage::write_data(&file_name, &text)?;
+        Ok(file_name)
+    }
+
+    pub fn load(file_name: &str) -> std::io::Result<Replay> {
+        serde_json::from_str(&storage::read_data(file_name)?).map_err(std::io::Error::other)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+// YYYY-MM-DD in UTC
+pub fn format_date(date: u64) -> String {
+    // days to a civil date, after Howard Hinnant's days_from_civil inverse
+    let days = (date / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{year:04}-{month:02}-{day:02}")
+}
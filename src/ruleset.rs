@@ -0,0 +1,255 @@
+use std::{fmt, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    GameState, PlayfieldSize,
+    garbage::{AttackTable, DEFAULT_GARBAGE_DELAY_MS},
+    scoring,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationSystem {
+    // guideline rotation with its five kick tests
+    #[default]
+    Srs,
+    // rotates in place or not at all
+    NoKicks,
+}
+
+impl RotationSystem {
+    pub fn kick_tests(self) -> usize {
+        match self {
+            RotationSystem::Srs => 5,
+            RotationSystem::NoKicks => 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Randomizer {
+    // every piece drawn independently
+    #[default]
+    Uniform,
+    // all seven pieces in a shuffled order, then the next seven
+    Bag,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockReset {
+    // the delay only starts over once the piece leaves the ground
+    #[default]
+    Step,
+    // moves and rotations on the ground start it over too, up to `limit` times per piece
+    Move {
+        limit: usize,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LockDelay {
+    pub delay_ms: usize,
+    pub reset: LockReset,
+}
+
+impl Default for LockDelay {
+    fn default() -> Self {
+        LockDelay {
+            delay_ms: 1000,
+            reset: LockReset::Step,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GravityCurve {
+    Constant { delay_ms: usize },
+    // the guideline speed up from a level one speed
+    Guideline { level_one_delay_ms: usize },
+}
+
+impl Default for GravityCurve {
+    fn default() -> Self {
+        GravityCurve::Guideline {
+            level_one_delay_ms: 200,
+        }
+    }
+}
+
+impl GravityCurve {
+    // time between two rows of fall at a level
+    pub fn delay_ms(&self, level: usize) -> usize {
+        match *self {
+            GravityCurve::Constant { delay_ms } => delay_ms.max(1),
+            GravityCurve::Guideline { level_one_delay_ms } => {
+                scoring::gravity_delay_ms(level_one_delay_ms, level)
+            }
+        }
+    }
+}
+
+pub const DEFAULT_LINE_CLEAR_DELAY_MS: usize = 300;
+
+// the smallest board every piece fits on in every rotation
+pub const MIN_COLS: isize = 4;
+pub const MIN_VISIBLE_ROWS: isize = 4;
+// a side longer than this is more likely a typo than a board
+pub const MAX_SIDE: isize = 1000;
+
+// the rules a game is played by; every field has a default, so a data file only needs the
+// ones it changes
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ruleset {
+    pub cols: isize,
+    pub visible_rows: isize,
+    // rows above the visible ones, pieces spawn in them and can be stacked into them
+    pub hidden_rows: isize,
+    pub rotation: RotationSystem,
+    pub randomizer: Randomizer,
+    pub lock_delay: LockDelay,
+    pub gravity: GravityCurve,
+    pub hold: bool,
+    // pieces shown after the current one
+    pub preview: usize,
+    pub attack_table: AttackTable,
+    pub garbage_delay_ms: usize,
+    // how long full rows stay on the board before they clear and the next piece comes, 0
+    // clears them at once
+    pub line_clear_delay_ms: usize,
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Ruleset {
+            cols: 10,
+            visible_rows: 20,
+            hidden_rows: 2,
+            rotation: RotationSystem::default(),
+            randomizer: Randomizer::default(),
+            lock_delay: LockDelay::default(),
+            gravity: GravityCurve::default(),
+            hold: true,
+            preview: 5,
+            attack_table: AttackTable::default(),
+            garbage_delay_ms: DEFAULT_GARBAGE_DELAY_MS,
+            line_clear_delay_ms: DEFAULT_LINE_CLEAR_DELAY_MS,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RulesetError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    // the rules parsed but can't be played, the message says which one and why
+    Invalid(String),
+}
+
+impl fmt::Display for RulesetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RulesetError::Io(error) => write!(f, "{error}"),
+            RulesetError::Parse(error) => write!(f, "{error}"),
+            RulesetError::Invalid(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for RulesetError {}
+
+impl Ruleset {
+    // the modern guideline: 7 bag, move reset lock delay and a 1 second first level
+    pub fn guideline() -> Ruleset {
+        Ruleset {
+            randomizer: Randomizer::Bag,
+            lock_delay: LockDelay {
+                delay_ms: 500,
+                reset: LockReset::Move { limit: 15 },
+            },
+            gravity: GravityCurve::Guideline {
+                level_one_delay_ms: 1000,
+            },
+            ..Ruleset::default()
+        }
+    }
+
+    // a board of `size` with no hidden rows and otherwise default rules
+    pub fn with_size(size: PlayfieldSize) -> Ruleset {
+        Ruleset {
+            cols: size.cols,
+            visible_rows: size.rows,
+            hidden_rows: 0,
+            ..Ruleset::default()
+        }
+    }
+
+    pub fn size(&self) -> PlayfieldSize {
+        PlayfieldSize {
+            rows: self.visible_rows + self.hidden_rows,
+            cols: self.cols,
+        }
+    }
+
+    pub fn new_game(&self) -> GameState {
+        GameState::from_ruleset(self.clone())
+    }
+
+    // a hash of every rule that stays the same across builds and platforms, so records set
+    // under different rules can be told apart
+    pub fn fingerprint(&self) -> u64 {
+        // FNV-1a over the rules as json, field order is fixed by the struct
+        let json = serde_json::to_string(self).unwrap_or_default();
+        json.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+
+    // rules a game can be built from; deserializing checks none of this
+    pub fn validate(&self) -> Result<(), RulesetError> {
+        let check = |ok: bool, message: String| {
+            if ok {
+                Ok(())
+            } else {
+                Err(RulesetError::Invalid(message))
+            }
+        };
+        check(
+            (MIN_COLS..=MAX_SIDE).contains(&self.cols),
+            format!("cols is {}, it must be {MIN_COLS} to {MAX_SIDE}", self.cols),
+        )?;
+        check(
+            (MIN_VISIBLE_ROWS..=MAX_SIDE).contains(&self.visible_rows),
+            format!(
+                "visible_rows is {}, it must be {MIN_VISIBLE_ROWS} to {MAX_SIDE}",
+                self.visible_rows
+            ),
+        )?;
+        check(
+            (0..=MAX_SIDE).contains(&self.hidden_rows),
+            format!(
+                "hidden_rows is {}, it must be 0 to {MAX_SIDE}",
+                self.hidden_rows
+            ),
+        )?;
+        check(
+            self.preview > 0,
+            "preview is 0, at least the next piece has to be shown".to_string(),
+        )
+    }
+
+    pub fn from_toml(text: &str) -> Result<Ruleset, RulesetError> {
+        let ruleset: Ruleset = toml::from_str(text).map_err(RulesetError::Parse)?;
+        ruleset.validate()?;
+        Ok(ruleset)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Ruleset, RulesetError> {
+        Ruleset::from_toml(&std::fs::read_to_string(path).map_err(RulesetError::Io)?)
+    }
+}
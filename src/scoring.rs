@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::TetraminoKind;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpinKind {
+    #[default]
+    None,
+    Mini,
+    Full,
+}
+
+// what a single lock did to the board, as scored by the guideline rules
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineClear {
+    pub kind: TetraminoKind,
+    pub lines: usize,
+    pub spin: SpinKind,
+    pub perfect_clear: bool,
+    // consecutive clears before this one, None when the combo was broken
+    pub combo: Option<usize>,
+    pub back_to_back: bool,
+    pub points: usize,
+}
+
+impl LineClear {
+    pub fn is_difficult(&self) -> bool {
+        self.lines == 4 || (self.lines > 0 && self.spin != SpinKind::None)
+    }
+}
+
+pub const DEFAULT_LINES_PER_LEVEL: usize = 10;
+
+#[derive(Clone)]
+pub struct Scoring {
+    score: usize,
+    start_level: usize,
+    level: usize,
+    lines_per_level: usize,
+    combo: Option<usize>,
+    back_to_back: bool,
+    last_clear: Option<LineClear>,
+    clear_counts: HashMap<(SpinKind, usize), usize>,
+}
+
+impl Scoring {
+    pub fn new(start_level: usize, lines_per_level: usize) -> Scoring {
+        Scoring {
+            score: 0,
+            start_level,
+            level: start_level,
+            lines_per_level,
+            combo: None,
+            back_to_back: false,
+            last_clear: None,
+            clear_counts: HashMap::new(),
+        }
+    }
+
+    pub fn score(&self) -> usize {
+        self.score
+    }
+    pub fn level(&self) -> usize {
+        self.level
+    }
+    pub fn combo(&self) -> Option<usize> {
+        self.combo
+    }
+    pub fn back_to_back(&self) -> bool {
+        self.back_to_back
+    }
+    pub fn last_clear(&self) -> Option<&LineClear> {
+        self.last_clear.as_ref()
+    }
+    // how many locks cleared this many lines with this kind of spin
+    pub fn clear_count(&self, spin: SpinKind, lines: usize) -> usize {
+        self.clear_counts.get(&(spin, lines)).copied().unwrap_or(0)
+    }
+
+    // soft drop scores one point per row, hard drop two
+    pub fn add_drop(&mut self, rows: usize, hard: bool) {
+        self.score += if hard { 2 * rows } else { rows };
+    }
+
+    pub fn lock(
+        &mut self,
+        kind: TetraminoKind,
+        lines: usize,
+        spin: SpinKind,
+        perfect_clear: bool,
+        total_lines: usize,
+    ) -> LineClear {
+        let base = match (spin, lines) {
+            (SpinKind::None, 0) => 0,
+            (SpinKind::None, 1) => 100,
+            (SpinKind::None, 2) => 300,
+            (SpinKind::None, 3) => 500,
+            (SpinKind::None, _) => 800,
+            (SpinKind::Mini, 0) => 100,
+            (SpinKind::Mini, 1) => 200,
+            (SpinKind::Mini, _) => 400,
+            (SpinKind::Full, 0) => 400,
+            (SpinKind::Full, 1) => 800,
+            (SpinKind::Full, 2) => 1200,
+            (SpinKind::Full, _) => 1600,
+        };
+
+        let mut clear = LineClear {
+            kind,
+            lines,
+            spin,
+            perfect_clear,
+            combo: None,
+            back_to_back: false,
+            points: 0,
+        };
+        let mut points = base * self.level;
+        if lines > 0 {
+            clear.back_to_back = clear.is_difficult() && self.back_to_back;
+            if clear.back_to_back {
+                points += points / 2;
+            }
+            self.back_to_back = clear.is_difficult();
+
+            self.combo = Some(self.combo.map_or(0, |combo| combo + 1));
+            clear.combo = self.combo;
+            points += 50 * self.combo.unwrap_or(0) * self.level;
+
+            if perfect_clear {
+                points += match lines {
+                    1 => 800,
+                    2 => 1200,
+                    3 => 1800,
+                    _ if clear.back_to_back => 3200,
+                    _ => 2000,
+                } * self.level;
+            }
+        } else {
+            self.combo = None;
+        }
+
+        if lines > 0 || spin != SpinKind::None {
+            *self.clear_counts.entry((spin, lines)).or_default() += 1;
+        }
+        clear.points = points;
+        self.score += points;
+        self.level = self.start_level + total_lines / self.lines_per_level.max(1);
+        self.last_clear = Some(clear);
+        clear
+    }
+}
+
+// guideline gravity curve relative to level 1: (0.8 - (level - 1) * 0.007) ^ (level - 1)
+pub fn gravity_delay_ms(level_one_delay_ms: usize, level: usize) -> usize {
+    let steps = level.saturating_sub(1) as f64;
+    let factor = (0.8 - steps * 0.007).max(0.0).powf(steps);
+    ((level_one_delay_ms as f64 * factor) as usize).max(1)
+}
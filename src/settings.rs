@@ -0,0 +1,490 @@
+use std::fmt;
+
+use macroquad::{
+    color::{Color, GRAY, WHITE},
+    input::KeyCode,
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{GARBAGE_COLOR, KeyMap, storage};
+
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+// how held keys repeat
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Handling {
+    // time a side key is held before it starts repeating
+    pub das_ms: usize,
+    // time between repeats once charged, 0 goes straight to the wall
+    pub arr_ms: usize,
+    // how many times faster than gravity soft drop falls, 0 drops to the floor at once
+    pub soft_drop_factor: usize,
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Handling {
+            das_ms: 133,
+            arr_ms: 0,
+            soft_drop_factor: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Skin {
+    // every piece in its own colour
+    #[default]
+    Guideline,
+    // pieces all look alike, garbage stays apart
+    Monochrome,
+}
+
+impl Skin {
+    pub const ALL: [Skin; 2] = [Skin::Guideline, Skin::Monochrome];
+
+    // the colour a block of `color` is drawn with
+    pub fn color(self, color: Color) -> Color {
+        match self {
+            Skin::Guideline => color,
+            Skin::Monochrome if color == GARBAGE_COLOR => GRAY,
+            Skin::Monochrome => WHITE,
+        }
+    }
+}
+
+// the mode started when no mode is given on the command line
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultMode {
+    #[default]
+    Endless,
+    Sprint,
+    Ultra,
+    Blitz,
+    Marathon,
+    Dig,
+    Practice,
+}
+
+impl DefaultMode {
+    pub const ALL: [DefaultMode; 7] = [
+        DefaultMode::Endless,
+        DefaultMode::Sprint,
+        DefaultMode::Ultra,
+        DefaultMode::Blitz,
+        DefaultMode::Marathon,
+        DefaultMode::Dig,
+        DefaultMode::Practice,
+    ];
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub keys: KeyMap,
+    // the second player of a local versus match, sharing the keyboard
+    pub keys_p2: KeyMap,
+    pub handling: Handling,
+    // 0 is silent, 1 is full volume
+    pub volume: f32,
+    // silences effects and music without losing the volume
+    pub muted: bool,
+    pub skin: Skin,
+    pub cell_size: f32,
+    pub grid_spacing: f32,
+    pub default_mode: DefaultMode,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            keys: KeyMap::default(),
+            keys_p2: KeyMap::arrows(),
+            handling: Handling::default(),
+            volume: 1.0,
+            muted: false,
+            skin: Skin::default(),
+            cell_size: 10.0,
+            grid_spacing: 5.0,
+            default_mode: DefaultMode::default(),
+        }
+    }
+}
+
+// an entry of the settings file that couldn't be used, its default is kept instead
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SettingsProblem {
+    pub entry: String,
+    pub message: String,
+}
+
+impl fmt::Display for SettingsProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.entry, self.message)
+    }
+}
+
+// every key that can be bound, by the name it has in the settings file
+const KEYS: [KeyCode; 121] = [
+    KeyCode::Space,
+    KeyCode::Apostrophe,
+    KeyCode::Comma,
+    KeyCode::Minus,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Semicolon,
+    KeyCode::Equal,
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::LeftBracket,
+    KeyCode::Backslash,
+    KeyCode::RightBracket,
+    KeyCode::GraveAccent,
+    KeyCode::World1,
+    KeyCode::World2,
+    KeyCode::Escape,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Right,
+    KeyCode::Left,
+    KeyCode::Down,
+    KeyCode::Up,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::CapsLock,
+    KeyCode::ScrollLock,
+    KeyCode::NumLock,
+    KeyCode::PrintScreen,
+    KeyCode::Pause,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::F13,
+    KeyCode::F14,
+    KeyCode::F15,
+    KeyCode::F16,
+    KeyCode::F17,
+    KeyCode::F18,
+    KeyCode::F19,
+    KeyCode::F20,
+    KeyCode::F21,
+    KeyCode::F22,
+    KeyCode::F23,
+    KeyCode::F24,
+    KeyCode::F25,
+    KeyCode::Kp0,
+    KeyCode::Kp1,
+    KeyCode::Kp2,
+    KeyCode::Kp3,
+    KeyCode::Kp4,
+    KeyCode::Kp5,
+    KeyCode::Kp6,
+    KeyCode::Kp7,
+    KeyCode::Kp8,
+    KeyCode::Kp9,
+    KeyCode::KpDecimal,
+    KeyCode::KpDivide,
+    KeyCode::KpMultiply,
+    KeyCode::KpSubtract,
+    KeyCode::KpAdd,
+    KeyCode::KpEnter,
+    KeyCode::KpEqual,
+    KeyCode::LeftShift,
+    KeyCode::LeftControl,
+    KeyCode::LeftAlt,
+    KeyCode::LeftSuper,
+    KeyCode::RightShift,
+    KeyCode::RightControl,
+    KeyCode::RightAlt,
+    KeyCode::RightSuper,
+    KeyCode::Menu,
+    KeyCode::Back,
+];
+
+pub fn key_name(key: KeyCode) -> String {
+    format!("{key:?}")
+}
+
+// names are matched ignoring case, so "space" and "Space" are the same key
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    KEYS.into_iter()
+        .find(|key| key_name(*key).eq_ignore_ascii_case(name.trim()))
+}
+
+// the actions of a key map by their names in the settings file
+pub const KEY_ACTIONS: [&str; 8] = [
+    "left",
+    "right",
+    "rotate_clockwise",
+    "rotate_counter_clockwise",
+    "soft_drop",
+    "hard_drop",
+    "hold",
+    "skip",
+];
+
+pub fn binding(keys: &KeyMap, action: &str) -> Option<KeyCode> {
+    let mut keys = keys.clone();
+    binding_mut(&mut keys, action).map(|key| *key)
+}
+
+pub fn binding_mut<'a>(keys: &'a mut KeyMap, action: &str) -> Option<&'a mut KeyCode> {
+    match action {
+        "left" => Some(&mut keys.left),
+        "right" => Some(&mut keys.right),
+        "rotate_clockwise" => Some(&mut keys.rotate_clockwise),
+        "rotate_counter_clockwise" => Some(&mut keys.rotate_counter_clockwise),
+        "soft_drop" => Some(&mut keys.soft_drop),
+        "hard_drop" => Some(&mut keys.hard_drop),
+        "hold" => Some(&mut keys.hold),
+        "skip" => Some(&mut keys.skip),
+        _ => None,
+    }
+}
+
+// the bindings of a [keys] table, entry is the table's name
+fn read_keys(
+    keys: &mut KeyMap,
+    entry: &str,
+    table: toml::Table,
+    report: &mut impl FnMut(&str, String),
+) {
+    for (action, item) in table {
+        let name = format!("{entry}.{action}");
+        let key = value::<String>(item)
+            .and_then(|key| parse_key(&key).ok_or(format!("there's no key called \"{key}\"")));
+        match (binding_mut(keys, &action), key) {
+            (None, _) => report(&name, "not an action".to_string()),
+            (Some(_), Err(message)) => report(&name, message),
+            (Some(binding), Ok(key)) => *binding = key,
+        }
+    }
+}
+
+fn keys_table(keys: &KeyMap) -> toml::Value {
+    let keys = KEY_ACTIONS
+        .iter()
+        .map(|action| {
+            let key = binding(keys, action).unwrap();
+            (action.to_string(), toml::Value::String(key_name(key)))
+        })
+        .collect();
+    toml::Value::Table(keys)
+}
+
+fn value<T: DeserializeOwned>(value: toml::Value) -> Result<T, String> {
+    value
+        .try_into()
+        .map_err(|error: toml::de::Error| error.message().trim().to_string())
+}
+
+fn in_range<T: PartialOrd + fmt::Display>(value: T, min: T, max: T) -> Result<T, String> {
+    if value < min || value > max {
+        Err(format!("{value} is outside {min} to {max}"))
+    } else {
+        Ok(value)
+    }
+}
+
+fn table(entry: &str, value: toml::Value) -> Result<toml::Table, String> {
+    match value {
+        toml::Value::Table(table) => Ok(table),
+        _ => Err(format!("{entry} should be a table")),
+    }
+}
+
+impl Settings {
+    // every entry is read on its own, a bad one is reported and keeps its default so the
+    // rest of the file still applies; two actions on the same key are reported as well
+    pub fn from_toml(text: &str) -> (Settings, Vec<SettingsProblem>) {
+        let mut settings = Settings::default();
+        let mut problems = Vec::new();
+        let mut report = |entry: &str, message: String| {
+            problems.push(SettingsProblem {
+                entry: entry.to_string(),
+                message,
+            })
+        };
+        let file: toml::Table = match text.parse() {
+            Ok(file) => file,
+            Err(error) => {
+                let error: toml::de::Error = error;
+                let line = error
+                    .span()
+                    .map_or(1, |span| text[..span.start].matches('\n').count() + 1);
+                report(
+                    SETTINGS_FILE,
+                    format!("line {line}: {}", error.message().trim()),
+                );
+                return (settings, problems);
+            }
+        };
+        for (entry, item) in file {
+            let result = match entry.as_str() {
+                "keys" => table(&entry, item)
+                    .map(|keys| read_keys(&mut settings.keys, &entry, keys, &mut report)),
+                "keys_p2" => table(&entry, item)
+                    .map(|keys| read_keys(&mut settings.keys_p2, &entry, keys, &mut report)),
+                "handling" => table(&entry, item).map(|handling| {
+                    for (field, item) in handling {
+                        let name = format!("handling.{field}");
+                        let (target, max) = match field.as_str() {
+                            "das_ms" => (&mut settings.handling.das_ms, 1000),
+                            "arr_ms" => (&mut settings.handling.arr_ms, 1000),
+                            "soft_drop_factor" => (&mut settings.handling.soft_drop_factor, 100),
+                            _ => {
+                                report(&name, "not a handling setting".to_string());
+                                continue;
+                            }
+                        };
+                        match value(item).and_then(|value| in_range(value, 0, max)) {
+                            Ok(value) => *target = value,
+                            Err(message) => report(&name, message),
+                        }
+                    }
+                }),
+                "volume" => value(item)
+                    .and_then(|volume| in_range(volume, 0.0, 1.0))
+                    .map(|volume| settings.volume = volume),
+                "muted" => value(item).map(|muted| settings.muted = muted),
+                "skin" => value(item).map(|skin| settings.skin = skin),
+                "cell_size" => value(item)
+                    .and_then(|size| in_range(size, 2.0, 64.0))
+                    .map(|size| settings.cell_size = size),
+                "grid_spacing" => value(item)
+                    .and_then(|spacing| in_range(spacing, 0.0, 32.0))
+                    .map(|spacing| settings.grid_spacing = spacing),
+                "default_mode" => value(item).map(|mode| settings.default_mode = mode),
+                _ => Err("not a setting".to_string()),
+            };
+            if let Err(message) = result {
+                report(&entry, message);
+            }
+        }
+        for (entry, keys) in [("keys", &settings.keys), ("keys_p2", &settings.keys_p2)] {
+            for (i, first) in KEY_ACTIONS.iter().enumerate() {
+                for second in &KEY_ACTIONS[i + 1..] {
+                    let key = binding(keys, first);
+                    if key == binding(keys, second) {
+                        report(
+                            &format!("{entry}.{second}"),
+                            format!("{} is already bound to {first}", key_name(key.unwrap())),
+                        );
+                    }
+                }
+            }
+        }
+        // both players press their keys on one keyboard, skip is left out as versus ignores it
+        let playing = || KEY_ACTIONS.iter().filter(|action| **action != "skip");
+        for second in playing() {
+            let key = binding(&settings.keys_p2, second);
+            if let Some(first) = playing().find(|first| binding(&settings.keys, first) == key) {
+                report(
+                    &format!("keys_p2.{second}"),
+                    format!(
+                        "{} is already bound to {first} of player 1",
+                        key_name(key.unwrap())
+                    ),
+                );
+            }
+        }
+        (settings, problems)
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut file = toml::Table::new();
+        file.insert("volume".into(), toml::Value::Float(self.volume as f64));
+        file.insert("muted".into(), toml::Value::Boolean(self.muted));
+        file.insert("skin".into(), toml::Value::try_from(self.skin).unwrap());
+        file.insert(
+            "cell_size".into(),
+            toml::Value::Float(self.cell_size as f64),
+        );
+        file.insert(
+            "grid_spacing".into(),
+            toml::Value::Float(self.grid_spacing as f64),
+        );
+        file.insert(
+            "default_mode".into(),
+            toml::Value::try_from(self.default_mode).unwrap(),
+        );
+        file.insert("keys".into(), keys_table(&self.keys));
+        file.insert("keys_p2".into(), keys_table(&self.keys_p2));
+        file.insert(
+            "handling".into(),
+            toml::Value::try_from(self.handling).unwrap(),
+        );
+        file.to_string()
+    }
+
+    // the settings in the config dir, defaults if there's no file yet
+    pub fn load() -> (Settings, Vec<SettingsProblem>) {
+        match storage::read_config(SETTINGS_FILE) {
+            Ok(text) => Settings::from_toml(&text),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                (Settings::default(), Vec::new())
+            }
+            Err(error) => (
+                Settings::default(),
+                vec![SettingsProblem {
+                    entry: SETTINGS_FILE.to_string(),
+                    message: error.to_string(),
+                }],
+            ),
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        storage::write_config(SETTINGS_FILE, &self.to_toml())
+    }
+}
@@ -0,0 +1,311 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+use macroquad::color::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::{GameState, PlayfieldSize, Position, TetraminoKind};
+
+// a full snapshot goes out this often so a viewer that missed something recovers
+pub const KEYFRAME_INTERVAL: usize = 300;
+
+// viewers are written to without blocking; one that falls this many bytes behind is dropped
+// instead of being buffered for
+const MAX_BACKLOG: usize = 1 << 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cell {
+    pub position: Position,
+    pub color: [u8; 4],
+}
+
+impl Cell {
+    pub fn color(&self) -> Color {
+        self.color.into()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivePiece {
+    pub kind: TetraminoKind,
+    pub cells: Vec<Cell>,
+}
+
+// everything apart from the placed cells, small enough to resend whenever it changes
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Status {
+    pub active: ActivePiece,
+    // as many pieces as the ruleset previews, the next one first
+    pub preview: Vec<TetraminoKind>,
+    pub hold: Option<TetraminoKind>,
+    pub score: usize,
+    pub level: usize,
+    pub lines: usize,
+    pub game_over: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub frame: usize,
+    pub rows: isize,
+    pub cols: isize,
+    // rows at the top the player doesn't see, a viewer leaves them out too
+    pub hidden_rows: isize,
+    pub cells: Vec<Cell>,
+    pub status: Status,
+}
+
+impl Snapshot {
+    pub fn new(game_state: &GameState, frame: usize) -> Snapshot {
+        let PlayfieldSize { rows, cols } = game_state.playfield_size();
+        let active = game_state.current_tetramino();
+        let mut cells: Vec<Cell> = game_state
+            .placed_blocks()
+            .get_blocks()
+            .iter()
+            .map(|block| Cell {
+                position: block.coordinates,
+                color: block.color.into(),
+            })
+            .collect();
+        cells.sort_by_key(|cell| (cell.position.row, cell.position.col));
+        let mut active_cells: Vec<Cell> = active
+            .get_blocks_with_offset()
+            .iter()
+            .map(|block| Cell {
+                position: block.coordinates,
+                color: block.color.into(),
+            })
+            .collect();
+        active_cells.sort_by_key(|cell| (cell.position.row, cell.position.col));
+        Snapshot {
+            frame,
+            rows,
+            cols,
+            hidden_rows: game_state.playfield().hidden_rows(),
+            cells,
+            status: Status {
+                active: ActivePiece {
+                    kind: active.kind(),
+                    cells: active_cells,
+                },
+                preview: game_state.next_queue(),
+                hold: game_state.hold_kind(),
+                score: game_state.score(),
+                level: game_state.level(),
+                lines: game_state.lines_cleared(),
+                game_over: game_state.is_game_over(),
+            },
+        }
+    }
+
+    // what changed since `previous`, None when nothing did
+    pub fn delta_from(&self, previous: &Snapshot) -> Option<Delta> {
+        let before: HashMap<Position, [u8; 4]> = previous
+            .cells
+            .iter()
+            .map(|cell| (cell.position, cell.color))
+            .collect();
+        let after: HashMap<Position, [u8; 4]> = self
+            .cells
+            .iter()
+            .map(|cell| (cell.position, cell.color))
+            .collect();
+        let added: Vec<Cell> = self
+            .cells
+            .iter()
+            .filter(|cell| before.get(&cell.position) != Some(&cell.color))
+            .copied()
+            .collect();
+        let removed: Vec<Position> = previous
+            .cells
+            .iter()
+            .map(|cell| cell.position)
+            .filter(|position| !after.contains_key(position))
+            .collect();
+        if added.is_empty() && removed.is_empty() && self.status == previous.status {
+            return None;
+        }
+        Some(Delta {
+            frame: self.frame,
+            added,
+            removed,
+            status: self.status.clone(),
+        })
+    }
+
+    pub fn apply(&mut self, delta: Delta) {
+        self.cells
+            .retain(|cell| !delta.removed.contains(&cell.position));
+        for cell in delta.added {
+            match self
+                .cells
+                .iter_mut()
+                .find(|existing| existing.position == cell.position)
+            {
+                Some(existing) => *existing = cell,
+                None => self.cells.push(cell),
+            }
+        }
+        self.frame = delta.frame;
+        self.status = delta.status;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Delta {
+    pub frame: usize,
+    pub added: Vec<Cell>,
+    pub removed: Vec<Position>,
+    pub status: Status,
+}
+
+// one JSON object per line, viewers never send anything back
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SpectatorMessage {
+    Snapshot(Snapshot),
+    Delta(Delta),
+}
+
+fn to_line(message: &SpectatorMessage) -> Vec<u8> {
+    let mut line = serde_json::to_vec(message).expect("spectator messages always serialize");
+    line.push(b'\n');
+    line
+}
+
+struct ViewerSocket {
+    stream: TcpStream,
+    // what the socket couldn't take yet, sent before anything newer
+    pending: Vec<u8>,
+}
+
+impl ViewerSocket {
+    // queues the line and writes as much as the socket takes right now; false once the viewer
+    // is gone or more than MAX_BACKLOG behind
+    fn send(&mut self, line: &[u8]) -> bool {
+        self.pending.extend_from_slice(line);
+        while !self.pending.is_empty() {
+            match self.stream.write(&self.pending) {
+                Ok(0) => return false,
+                Ok(written) => {
+                    self.pending.drain(..written);
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                    return self.pending.len() <= MAX_BACKLOG;
+                }
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => return false,
+            }
+        }
+        true
+    }
+}
+
+// publishes a running game to every viewer that connects
+pub struct Broadcaster {
+    listener: TcpListener,
+    viewers: Vec<ViewerSocket>,
+    frame: usize,
+    last: Option<Snapshot>,
+}
+
+impl Broadcaster {
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Broadcaster> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Broadcaster {
+            listener,
+            viewers: Vec::new(),
+            frame: 0,
+            last: None,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.listener.local_addr()
+    }
+    pub fn viewers(&self) -> usize {
+        self.viewers.len()
+    }
+
+    // call once per frame; new viewers get a full snapshot, the rest only what changed
+    pub fn publish(&mut self, game_state: &GameState) {
+        let snapshot = Snapshot::new(game_state, self.frame);
+        self.frame += 1;
+
+        let keyframe = self.frame.is_multiple_of(KEYFRAME_INTERVAL);
+        let message = match &self.last {
+            Some(last) if !keyframe => snapshot.delta_from(last).map(SpectatorMessage::Delta),
+            _ => Some(SpectatorMessage::Snapshot(snapshot.clone())),
+        };
+        if let Some(message) = message {
+            let line = to_line(&message);
+            self.viewers.retain_mut(|viewer| viewer.send(&line));
+        }
+
+        let full = to_line(&SpectatorMessage::Snapshot(snapshot.clone()));
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_err() || stream.set_nodelay(true).is_err() {
+                continue;
+            }
+            let mut viewer = ViewerSocket {
+                stream,
+                pending: Vec::new(),
+            };
+            if viewer.send(&full) {
+                self.viewers.push(viewer);
+            }
+        }
+        self.last = Some(snapshot);
+    }
+}
+
+// the receiving end, rebuilds the game as the broadcaster sees it
+pub struct Viewer {
+    reader: BufReader<TcpStream>,
+    partial: String,
+    state: Option<Snapshot>,
+}
+
+impl Viewer {
+    pub fn connect(address: impl ToSocketAddrs) -> io::Result<Viewer> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nonblocking(true)?;
+        Ok(Viewer {
+            reader: BufReader::new(stream),
+            partial: String::new(),
+            state: None,
+        })
+    }
+
+    pub fn state(&self) -> Option<&Snapshot> {
+        self.state.as_ref()
+    }
+
+    // applies everything received so far without blocking, Ok(false) once the game went away
+    pub fn poll(&mut self) -> io::Result<bool> {
+        loop {
+            match self.reader.read_line(&mut self.partial) {
+                Ok(0) => return Ok(false),
+                Ok(_) if self.partial.ends_with('\n') => {
+                    let message = serde_json::from_str(self.partial.trim_end())
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    self.partial.clear();
+                    match (message, self.state.as_mut()) {
+                        (SpectatorMessage::Snapshot(snapshot), _) => self.state = Some(snapshot),
+                        (SpectatorMessage::Delta(delta), Some(state)) => state.apply(delta),
+                        // deltas before the first snapshot have nothing to apply to
+                        (SpectatorMessage::Delta(_), None) => {}
+                    }
+                }
+                Ok(_) => {}
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(true),
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::{fs, io, path::PathBuf};
+
+// a file in the tetrs directory under `base`, the directory is created if it's missing
+fn tetrs_path(base: Option<PathBuf>, kind: &str, file_name: &str) -> io::Result<PathBuf> {
+    let dir = base
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no {kind} directory")))?
+        .join("tetrs");
+    fs::create_dir_all(&dir)?;
+    Ok(dir.join(file_name))
+}
+
+// everything tetrs keeps between runs lives in one directory under the user's data dir
+pub fn data_path(file_name: &str) -> io::Result<PathBuf> {
+    tetrs_path(dirs::data_dir(), "data", file_name)
+}
+
+pub fn read_data(file_name: &str) -> io::Result<String> {
+    fs::read_to_string(data_path(file_name)?)
+}
+
+pub fn write_data(file_name: &str, contents: &str) -> io::Result<()> {
+    fs::write(data_path(file_name)?, contents)
+}
+
+pub fn rename_data(from: &str, to: &str) -> io::Result<()> {
+    fs::rename(data_path(from)?, data_path(to)?)
+}
+
+// files meant to be edited by the player go under the config dir instead
+pub fn config_path(file_name: &str) -> io::Result<PathBuf> {
+    tetrs_path(dirs::config_dir(), "config", file_name)
+}
+
+pub fn read_config(file_name: &str) -> io::Result<String> {
+    fs::read_to_string(config_path(file_name)?)
+}
+
+pub fn write_config(file_name: &str, contents: &str) -> io::Result<()> {
+    fs::write(config_path(file_name)?, contents)
+}
@@ -0,0 +1,414 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Block, GameState, Position, RotationState, TetraminoKind,
+    bot::Decision,
+    move_generator::{CollisionMap, generate_placements_from},
+    scoring::SpinKind,
+};
+
+// the protocol always describes a 10 wide board of 40 rows, row 0 at the bottom
+pub const BOARD_ROWS: usize = 40;
+pub const BOARD_COLS: usize = 10;
+// how long a bot gets to exit after quit before it is killed
+const QUIT_TIMEOUT: Duration = Duration::from_millis(500);
+const QUIT_POLL: Duration = Duration::from_millis(10);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl From<RotationState> for Orientation {
+    fn from(value: RotationState) -> Self {
+        match value {
+            RotationState::Init => Orientation::North,
+            RotationState::Right => Orientation::East,
+            RotationState::Flip => Orientation::South,
+            RotationState::Left => Orientation::West,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PieceLocation {
+    #[serde(rename = "type")]
+    pub kind: TetraminoKind,
+    pub orientation: Orientation,
+    pub x: isize,
+    pub y: isize,
+}
+
+impl PieceLocation {
+    // the cells as (x, y) with y going up, relative to the piece center as the protocol defines it
+    fn shape(&self) -> [(isize, isize); 4] {
+        let north = match self.kind {
+            TetraminoKind::I => [(-1, 0), (0, 0), (1, 0), (2, 0)],
+            TetraminoKind::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+            TetraminoKind::T => [(-1, 0), (0, 0), (1, 0), (0, 1)],
+            TetraminoKind::L => [(-1, 0), (0, 0), (1, 0), (1, 1)],
+            TetraminoKind::J => [(-1, 0), (0, 0), (1, 0), (-1, 1)],
+            TetraminoKind::S => [(-1, 0), (0, 0), (0, 1), (1, 1)],
+            TetraminoKind::Z => [(-1, 1), (0, 1), (0, 0), (1, 0)],
+        };
+        north.map(|(x, y)| match self.orientation {
+            Orientation::North => (x, y),
+            Orientation::East => (y, -x),
+            Orientation::South => (-x, -y),
+            Orientation::West => (-y, x),
+        })
+    }
+
+    // the cells on a playfield of `rows` rows, where rows count down from the top
+    pub fn cells(&self, rows: isize) -> Vec<Position> {
+        self.shape()
+            .iter()
+            .map(|(x, y)| Position::new(rows - 1 - (self.y + y), self.x + x))
+            .collect()
+    }
+
+    // where a piece covering `cells` sits, in the orientation it was rotated into
+    pub fn from_cells(
+        kind: TetraminoKind,
+        rotation_state: RotationState,
+        cells: &[Position],
+        rows: isize,
+    ) -> PieceLocation {
+        let mut location = PieceLocation {
+            kind,
+            orientation: rotation_state.into(),
+            x: 0,
+            y: 0,
+        };
+        let lowest = |cells: &mut dyn Iterator<Item = (isize, isize)>| {
+            cells.min_by_key(|(x, y)| (*y, *x)).unwrap_or_default()
+        };
+        let (shape_x, shape_y) = lowest(&mut location.shape().into_iter());
+        let (x, y) = lowest(&mut cells.iter().map(|cell| (cell.col, rows - 1 - cell.row)));
+        location.x = x - shape_x;
+        location.y = y - shape_y;
+        location
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Move {
+    pub location: PieceLocation,
+    pub spin: SpinKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FrontendMessage {
+    Rules,
+    Start {
+        hold: Option<TetraminoKind>,
+        queue: Vec<TetraminoKind>,
+        combo: usize,
+        back_to_back: bool,
+        // filled cells are sent as garbage, bots only care whether a cell is free
+        board: Vec<Vec<Option<char>>>,
+    },
+    Stop,
+    Suggest,
+    Play {
+        #[serde(rename = "move")]
+        mv: Move,
+    },
+    NewPiece {
+        piece: TetraminoKind,
+    },
+    Quit,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotMessage {
+    Info {
+        name: String,
+        version: String,
+        author: String,
+        #[serde(default)]
+        features: Vec<String>,
+    },
+    Ready,
+    Error {
+        reason: String,
+    },
+    Suggestion {
+        moves: Vec<Move>,
+    },
+}
+
+#[derive(Debug)]
+pub enum TbpError {
+    Io(io::Error),
+    Protocol(String),
+    // the bot refused the game, with the reason it gave
+    Rejected(String),
+    // the game isn't one the protocol can describe
+    UnsupportedRules(String),
+    Exited,
+}
+
+impl fmt::Display for TbpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TbpError::Io(error) => write!(f, "bot process error: {error}"),
+            TbpError::Protocol(message) => write!(f, "bot protocol error: {message}"),
+            TbpError::Rejected(reason) => write!(f, "bot rejected the game: {reason}"),
+            TbpError::UnsupportedRules(reason) => {
+                write!(f, "can't hand the game to a bot: {reason}")
+            }
+            TbpError::Exited => write!(f, "bot exited"),
+        }
+    }
+}
+
+impl std::error::Error for TbpError {}
+
+impl From<io::Error> for TbpError {
+    fn from(value: io::Error) -> Self {
+        TbpError::Io(value)
+    }
+}
+
+// reads and writes newline separated JSON, shared by the frontend and bots written against it
+pub fn write_message(writer: &mut impl Write, message: &impl Serialize) -> io::Result<()> {
+    let mut line = serde_json::to_vec(message).map_err(io::Error::other)?;
+    line.push(b'\n');
+    writer.write_all(&line)?;
+    writer.flush()
+}
+
+pub fn read_message<T: for<'de> Deserialize<'de>>(
+    reader: &mut impl BufRead,
+) -> Result<Option<T>, TbpError> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    serde_json::from_str(line.trim_end())
+        .map(Some)
+        .map_err(|e| TbpError::Protocol(format!("{e} in {line:?}")))
+}
+
+// an external bot playing our game, launched as a child process
+pub struct TbpBot {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    name: String,
+    running: bool,
+}
+
+impl TbpBot {
+    pub fn launch(program: &str, args: &[String]) -> Result<TbpBot, TbpError> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(TbpError::Exited);
+        };
+        let mut bot = TbpBot {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            name: String::new(),
+            running: false,
+        };
+        match bot.receive()? {
+            BotMessage::Info { name, version, .. } => bot.name = format!("{name} {version}"),
+            other => return Err(TbpError::Protocol(format!("expected info, got {other:?}"))),
+        }
+        bot.send(&FrontendMessage::Rules)?;
+        match bot.receive()? {
+            BotMessage::Ready => Ok(bot),
+            BotMessage::Error { reason } => Err(TbpError::Rejected(reason)),
+            other => Err(TbpError::Protocol(format!("expected ready, got {other:?}"))),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn send(&mut self, message: &FrontendMessage) -> Result<(), TbpError> {
+        Ok(write_message(&mut self.stdin, message)?)
+    }
+
+    fn receive(&mut self) -> Result<BotMessage, TbpError> {
+        read_message(&mut self.stdout)?.ok_or(TbpError::Exited)
+    }
+
+    // tells the bot about the whole game from scratch, as it is after garbage or a restart
+    pub fn start(&mut self, game_state: &GameState) -> Result<(), TbpError> {
+        let size = game_state.playfield_size();
+        if size.cols != BOARD_COLS as isize || size.rows > BOARD_ROWS as isize {
+            return Err(TbpError::UnsupportedRules(format!(
+                "the board is {} wide and {} high, the protocol needs {BOARD_COLS} wide and at most {BOARD_ROWS} high",
+                size.cols, size.rows
+            )));
+        }
+        if self.running {
+            self.send(&FrontendMessage::Stop)?;
+        }
+        let rows = size.rows;
+        let mut board = vec![vec![None; BOARD_COLS]; BOARD_ROWS];
+        for block in game_state.placed_blocks().get_blocks() {
+            let y = rows - 1 - block.coordinates.row;
+            if let Some(cell) = usize::try_from(y)
+                .ok()
+                .and_then(|y| board.get_mut(y))
+                .and_then(|row| row.get_mut(block.coordinates.col as usize))
+            {
+                *cell = Some('G');
+            }
+        }
+        let scoring = game_state.scoring();
+        self.send(&FrontendMessage::Start {
+            hold: game_state.hold_kind(),
+            queue: std::iter::once(game_state.current_tetramino().kind())
+                .chain(game_state.preview(preview_count(game_state)))
+                .collect(),
+            combo: scoring.combo().map_or(0, |combo| combo + 1),
+            back_to_back: scoring.back_to_back(),
+            board,
+        })?;
+        self.running = true;
+        Ok(())
+    }
+
+    // the first suggested move that the piece can actually reach from where it is
+    pub fn suggest(
+        &mut self,
+        game_state: &GameState,
+    ) -> Result<Option<(Move, Decision)>, TbpError> {
+        self.send(&FrontendMessage::Suggest)?;
+        let moves = match self.receive()? {
+            BotMessage::Suggestion { moves } => moves,
+            other => {
+                return Err(TbpError::Protocol(format!(
+                    "expected suggestion, got {other:?}"
+                )));
+            }
+        };
+
+        let collision_map = CollisionMap::new(game_state.playfield());
+        let rows = game_state.playfield_size().rows;
+        let active = game_state.current_tetramino();
+        let held = game_state.hold_kind().unwrap_or(game_state.next_kind());
+        for mv in moves {
+            // a move for another piece than the active one means the bot wants to hold
+            let hold = mv.location.kind != active.kind();
+            let (rotation_state, offset) = match hold {
+                false => (active.rotation_state(), active.offset()),
+                true if game_state.can_hold() && mv.location.kind == held => {
+                    (RotationState::Init, game_state.playfield().spawn_offset())
+                }
+                true => continue,
+            };
+            let target: HashSet<Block> = mv
+                .location
+                .cells(rows)
+                .into_iter()
+                .map(Block::from)
+                .collect();
+            if let Some(placement) = generate_placements_from(
+                &collision_map,
+                &game_state.shape_table(mv.location.kind),
+                rotation_state,
+                offset,
+            )
+            .into_iter()
+            .find(|placement| placement.blocks == target)
+            {
+                return Ok(Some((mv, Decision { hold, placement })));
+            }
+        }
+        Ok(None)
+    }
+
+    // asks for a move and plays it; false when the bot had nothing playable or the game is over
+    pub fn play_piece(&mut self, game_state: &mut GameState) -> Result<bool, TbpError> {
+        if game_state.is_game_over() {
+            return Ok(false);
+        }
+        if !self.running {
+            self.start(game_state)?;
+        }
+        let Some((mv, decision)) = self.suggest(game_state)? else {
+            return Ok(false);
+        };
+
+        let hold_was_empty = game_state.hold_kind().is_none();
+        let cells_before = game_state.placed_blocks().get_blocks().len();
+        let lines_before = game_state.lines_cleared();
+        if decision.hold {
+            game_state.hold();
+        }
+        for input in decision.placement.inputs_before_hard_drop() {
+            game_state.apply_input(*input);
+        }
+        game_state.hard_drop();
+        self.send(&FrontendMessage::Play { mv })?;
+
+        // garbage changes the board behind the bot's back, it has to start over from the new one
+        let cols = game_state.playfield_size().cols as usize;
+        let cleared = game_state.lines_cleared() - lines_before;
+        let expected_cells = (cells_before + 4).saturating_sub(cleared * cols);
+        if game_state.placed_blocks().get_blocks().len() != expected_cells {
+            return self.start(game_state).map(|_| !game_state.is_game_over());
+        }
+
+        // holding into an empty slot takes one more piece from the queue
+        let revealed = if decision.hold && hold_was_empty {
+            2
+        } else {
+            1
+        };
+        let queue: Vec<TetraminoKind> = std::iter::once(game_state.current_tetramino().kind())
+            .chain(game_state.preview(preview_count(game_state)))
+            .collect();
+        for piece in &queue[queue.len() - revealed..] {
+            self.send(&FrontendMessage::NewPiece { piece: *piece })?;
+        }
+        Ok(!game_state.is_game_over())
+    }
+}
+
+// the bot sees what the ruleset previews, and always at least the next piece
+fn preview_count(game_state: &GameState) -> usize {
+    game_state.ruleset().preview.max(1)
+}
+
+impl Drop for TbpBot {
+    fn drop(&mut self) {
+        let _ = self.send(&FrontendMessage::Quit);
+        // a bot that ignores quit would hang the game on wait, so it only gets so long
+        let deadline = Instant::now() + QUIT_TIMEOUT;
+        while let Ok(None) = self.child.try_wait() {
+            if Instant::now() >= deadline {
+                let _ = self.child.kill();
+                let _ = self.child.wait();
+                return;
+            }
+            thread::sleep(QUIT_POLL);
+        }
+    }
+}
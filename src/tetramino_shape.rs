@@ -2,13 +2,15 @@ use std::collections::HashSet;
 
 use macroquad::color::*;
 use rand::{
-    distr::{Distribution, StandardUniform},
     Rng,
+    distr::{Distribution, StandardUniform},
 };
+use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
 
 use crate::{Block, Position};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter, Serialize, Deserialize)]
 pub enum TetraminoKind {
     I,
     L,
@@ -19,14 +21,14 @@ pub enum TetraminoKind {
     T,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum RotationDirection {
     Clockwise,
     CounterClockwise,
 }
 
-#[derive(Clone, Copy, Default)]
-enum RotationState {
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Hash, EnumIter, Serialize, Deserialize)]
+pub enum RotationState {
     #[default]
     Init,
     Right,
@@ -34,6 +36,20 @@ enum RotationState {
     Left,
 }
 
+impl TetraminoKind {
+    pub fn color(self) -> Color {
+        match self {
+            TetraminoKind::I => BLUE,
+            TetraminoKind::L => ORANGE,
+            TetraminoKind::J => DARKBLUE,
+            TetraminoKind::S => GREEN,
+            TetraminoKind::Z => RED,
+            TetraminoKind::O => YELLOW,
+            TetraminoKind::T => PURPLE,
+        }
+    }
+}
+
 impl Distribution<TetraminoKind> for StandardUniform {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> TetraminoKind {
         match rng.random_range(0usize..7) {
@@ -47,6 +63,7 @@ impl Distribution<TetraminoKind> for StandardUniform {
         }
     }
 }
+#[derive(Clone)]
 pub struct Tetramino {
     kind: TetraminoKind,
     rotation_center: Position,
@@ -76,21 +93,6 @@ impl Tetramino {
         }
     }
 
-    pub fn with_offset(self, offset: Position) -> Tetramino {
-        Tetramino {
-            kind: self.kind,
-            rotation_center: self.rotation_center,
-            rotation_state: self.rotation_state,
-            blocks: self
-                .blocks
-                .iter()
-                .map(|b| Block {
-                    color: b.color,
-                    coordinates: b.coordinates + offset,
-                })
-                .collect(),
-        }
-    }
     pub fn get_blocks_with_offset(&self, offset: Position) -> HashSet<Block> {
         self.blocks
             .iter()
@@ -103,6 +105,15 @@ impl Tetramino {
     pub fn get_blocks(&self) -> &HashSet<Block> {
         &self.blocks
     }
+    pub fn kind(&self) -> TetraminoKind {
+        self.kind
+    }
+    pub fn rotation_state(&self) -> RotationState {
+        self.rotation_state
+    }
+    pub fn rotation_center(&self) -> Position {
+        self.rotation_center
+    }
 
     // values from SRS implementation by TTC: https://tetris.wiki/Super_Rotation_System#How_Guideline_SRS_Really_Works
     // (x, y) from site -> (-y, x) in code # because y-axis in my implementation is flipped
@@ -243,7 +254,7 @@ impl Tetramino {
                         .iter()
                         .map(|(r, c)| -> Block {
                             Block {
-                                color: BLUE,
+                                color: kind.color(),
                                 coordinates: Position::new(*r, *c),
                             }
                         })
@@ -259,7 +270,7 @@ impl Tetramino {
                         .iter()
                         .map(|(row, col)| -> Block {
                             Block {
-                                color: ORANGE,
+                                color: kind.color(),
                                 coordinates: Position::new(*row, *col),
                             }
                         })
@@ -275,7 +286,7 @@ impl Tetramino {
                         .iter()
                         .map(|(row, col)| -> Block {
                             Block {
-                                color: DARKBLUE,
+                                color: kind.color(),
                                 coordinates: Position::new(*row, *col),
                             }
                         })
@@ -291,7 +302,7 @@ impl Tetramino {
                         .iter()
                         .map(|(row, col)| -> Block {
                             Block {
-                                color: GREEN,
+                                color: kind.color(),
                                 coordinates: Position::new(*row, *col),
                             }
                         })
@@ -307,7 +318,7 @@ impl Tetramino {
                         .iter()
                         .map(|(row, col)| -> Block {
                             Block {
-                                color: RED,
+                                color: kind.color(),
                                 coordinates: Position::new(*row, *col),
                             }
                         })
@@ -323,7 +334,7 @@ impl Tetramino {
                         .iter()
                         .map(|(row, col)| -> Block {
                             Block {
-                                color: YELLOW,
+                                color: kind.color(),
                                 coordinates: Position::new(*row, *col),
                             }
                         })
@@ -339,7 +350,7 @@ impl Tetramino {
                         .iter()
                         .map(|(row, col)| -> Block {
                             Block {
-                                color: PURPLE,
+                                color: kind.color(),
                                 coordinates: Position::new(*row, *col),
                             }
                         })
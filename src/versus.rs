@@ -0,0 +1,84 @@
+use crate::{GameState, ruleset::Ruleset};
+
+pub const DEFAULT_FIRST_TO: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VersusStatus {
+    Playing,
+    // None when both players topped out on the same frame
+    RoundOver { winner: Option<usize> },
+    MatchOver { winner: usize },
+}
+
+// two independent games linked through their garbage, played as rounds until one player has
+// won first_to of them
+pub struct Versus {
+    players: [GameState; 2],
+    wins: [usize; 2],
+    first_to: usize,
+    status: VersusStatus,
+    ruleset: Ruleset,
+}
+
+impl Versus {
+    pub fn new(first_to: usize, ruleset: Ruleset) -> Versus {
+        Versus {
+            players: [ruleset.new_game(), ruleset.new_game()],
+            wins: [0, 0],
+            first_to: first_to.max(1),
+            status: VersusStatus::Playing,
+            ruleset,
+        }
+    }
+
+    pub fn players(&self) -> &[GameState; 2] {
+        &self.players
+    }
+    pub fn players_mut(&mut self) -> &mut [GameState; 2] {
+        &mut self.players
+    }
+    pub fn wins(&self) -> [usize; 2] {
+        self.wins
+    }
+    pub fn first_to(&self) -> usize {
+        self.first_to
+    }
+    pub fn status(&self) -> VersusStatus {
+        self.status
+    }
+
+    pub fn update(&mut self) -> VersusStatus {
+        if self.status != VersusStatus::Playing {
+            return self.status;
+        }
+        let [first, second] = &mut self.players;
+        second.receive_garbage(first.take_outgoing_garbage());
+        first.receive_garbage(second.take_outgoing_garbage());
+
+        let topped_out = self.players.each_ref().map(|player| player.is_game_over());
+        if topped_out.iter().any(|topped_out| *topped_out) {
+            let winner = match topped_out {
+                [true, false] => Some(1),
+                [false, true] => Some(0),
+                _ => None,
+            };
+            if let Some(winner) = winner {
+                self.wins[winner] += 1;
+            }
+            self.status = match winner {
+                Some(winner) if self.wins[winner] >= self.first_to => {
+                    VersusStatus::MatchOver { winner }
+                }
+                _ => VersusStatus::RoundOver { winner },
+            };
+        }
+        self.status
+    }
+
+    pub fn next_round(&mut self) {
+        if let VersusStatus::RoundOver { .. } = self.status {
+            self.players = [(); 2].map(|_| self.ruleset.new_game());
+            self.status = VersusStatus::Playing;
+        }
+    }
+}